@@ -52,18 +52,24 @@
 //! let json = serde_json::to_value(&metadata).unwrap();
 //! ```
 
-use core::fmt::{Display, Formatter, Result as DisplayResult, Write};
+use core::{
+    fmt::{Display, Formatter, Result as DisplayResult, Write},
+    str::FromStr,
+};
 use semver::Version;
-use serde::{Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{Map, Value};
 use url::Url;
 
 const METADATA_VERSION: &str = "0.1.0";
 
 /// Smart contract metadata.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ContractMetadata {
-    #[serde(rename = "metadataVersion")]
+    #[serde(
+        rename = "metadataVersion",
+        deserialize_with = "deserialize_metadata_version"
+    )]
     metadata_version: semver::Version,
     source: Source,
     contract: Contract,
@@ -97,6 +103,31 @@ impl ContractMetadata {
     pub fn remove_source_wasm_attribute(&mut self) {
         self.source.wasm = None;
     }
+
+    /// The version of the metadata format.
+    pub fn metadata_version(&self) -> &Version {
+        &self.metadata_version
+    }
+
+    /// Information about the contract source and how it was built.
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    /// Metadata about the contract.
+    pub fn contract(&self) -> &Contract {
+        &self.contract
+    }
+
+    /// Additional user defined metadata.
+    pub fn user(&self) -> Option<&User> {
+        self.user.as_ref()
+    }
+
+    /// Raw JSON of the contract abi metadata.
+    pub fn abi(&self) -> &Map<String, Value> {
+        &self.abi
+    }
 }
 
 /// Representation of the Wasm code hash.
@@ -112,13 +143,43 @@ impl Serialize for CodeHash {
     }
 }
 
-#[derive(Clone, Debug, Serialize)]
+impl Display for CodeHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
+        write!(f, "0x")?;
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for CodeHash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = deserialize_from_byte_str(deserializer)?;
+        let mut hash = [0u8; 32];
+        if bytes.len() != hash.len() {
+            return Err(de::Error::custom(format!(
+                "Expected a code hash of 32 bytes, got {} bytes",
+                bytes.len()
+            )));
+        }
+        hash.copy_from_slice(&bytes);
+        Ok(CodeHash(hash))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Source {
     hash: CodeHash,
     language: SourceLanguage,
     compiler: SourceCompiler,
     #[serde(skip_serializing_if = "Option::is_none")]
     wasm: Option<SourceWasm>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build_info: Option<BuildInfo>,
 }
 
 impl Source {
@@ -134,8 +195,67 @@ impl Source {
             language,
             compiler,
             wasm,
+            build_info: None,
         }
     }
+
+    /// Records how the contract was built.
+    pub fn with_build_info(mut self, build_info: BuildInfo) -> Self {
+        self.build_info = Some(build_info);
+        self
+    }
+
+    /// The hash of the contract Wasm code.
+    pub fn hash(&self) -> &CodeHash {
+        &self.hash
+    }
+
+    /// The language the contract is written in.
+    pub fn language(&self) -> &SourceLanguage {
+        &self.language
+    }
+
+    /// The compiler the contract was compiled with.
+    pub fn compiler(&self) -> &SourceCompiler {
+        &self.compiler
+    }
+
+    /// The contract Wasm code, if it is included.
+    pub fn wasm(&self) -> Option<&SourceWasm> {
+        self.wasm.as_ref()
+    }
+
+    /// How the contract was built, if it was recorded.
+    pub fn build_info(&self) -> Option<&BuildInfo> {
+        self.build_info.as_ref()
+    }
+}
+
+/// Provenance of a contract build, allowing to trace an artifact back to how it was produced.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// Version of the tool which built the contract.
+    pub cargo_contract_version: String,
+    /// Version of `wasm-opt` the contract was optimized with, if it could be determined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wasm_opt_version: Option<String>,
+    /// Number of optimization passes passed to `wasm-opt`.
+    pub optimization_passes: String,
+    /// Whether the contract was built with debug functionality.
+    pub build_mode: String,
+    /// Whether symbols (Wasm name section) were kept when optimizing.
+    pub keep_debug_symbols: bool,
+    /// `RUSTFLAGS` the contract was compiled with.
+    pub rustflags: String,
+    /// Rustup toolchain the contract was compiled with, if known, e.g. `nightly-2021-09-01`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rust_toolchain: Option<String>,
+    /// Hash of the `Cargo.lock` of the contract, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cargo_lock_hash: Option<CodeHash>,
+    /// Commit of the git repository containing the contract, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
 }
 
 /// The bytes of the compiled Wasm smart contract.
@@ -149,6 +269,11 @@ impl SourceWasm {
     pub fn new(wasm: Vec<u8>) -> Self {
         SourceWasm { wasm }
     }
+
+    /// The bytes of the Wasm code.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.wasm
+    }
 }
 
 impl Serialize for SourceWasm {
@@ -160,6 +285,15 @@ impl Serialize for SourceWasm {
     }
 }
 
+impl<'de> Deserialize<'de> for SourceWasm {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(SourceWasm::new(deserialize_from_byte_str(deserializer)?))
+    }
+}
+
 impl Display for SourceWasm {
     fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
         write!(f, "0x").expect("failed writing to string");
@@ -193,6 +327,24 @@ impl Serialize for SourceLanguage {
     }
 }
 
+impl<'de> Deserialize<'de> for SourceLanguage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_from_str(deserializer)
+    }
+}
+
+impl FromStr for SourceLanguage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (language, version) = split_name_and_version(s)?;
+        Ok(SourceLanguage::new(language.parse()?, version))
+    }
+}
+
 impl Display for SourceLanguage {
     fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
         write!(f, "{} {}", self.language, self.version)
@@ -217,6 +369,19 @@ impl Display for Language {
     }
 }
 
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ink!" => Ok(Self::Ink),
+            "Solidity" => Ok(Self::Solidity),
+            "AssemblyScript" => Ok(Self::AssemblyScript),
+            _ => Err(format!("Invalid language '{}'", s)),
+        }
+    }
+}
+
 /// A compiler used to compile a smart contract.
 #[derive(Clone, Debug)]
 pub struct SourceCompiler {
@@ -239,6 +404,24 @@ impl Serialize for SourceCompiler {
     }
 }
 
+impl<'de> Deserialize<'de> for SourceCompiler {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_from_str(deserializer)
+    }
+}
+
+impl FromStr for SourceCompiler {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (compiler, version) = split_name_and_version(s)?;
+        Ok(SourceCompiler::new(compiler.parse()?, version))
+    }
+}
+
 impl SourceCompiler {
     pub fn new(compiler: Compiler, version: Version) -> Self {
         SourceCompiler { compiler, version }
@@ -246,7 +429,7 @@ impl SourceCompiler {
 }
 
 /// Compilers used to compile a smart contract.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Compiler {
     RustC,
     Solang,
@@ -261,8 +444,20 @@ impl Display for Compiler {
     }
 }
 
+impl FromStr for Compiler {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rustc" => Ok(Self::RustC),
+            "solang" => Ok(Self::Solang),
+            _ => Err(format!("Invalid compiler '{}'", s)),
+        }
+    }
+}
+
 /// Metadata about a smart contract.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Contract {
     name: String,
     version: Version,
@@ -283,10 +478,30 @@ impl Contract {
     pub fn builder() -> ContractBuilder {
         ContractBuilder::default()
     }
+
+    /// The name of the contract.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The version of the contract.
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// The authors of the contract.
+    pub fn authors(&self) -> &[String] {
+        &self.authors
+    }
+
+    /// The description of the contract, if any.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
 }
 
 /// Additional user defined metadata, can be any valid json.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct User {
     #[serde(flatten)]
     json: Map<String, Value>,
@@ -462,6 +677,66 @@ where
     serializer.serialize_str(&hex)
 }
 
+/// Deserializes the given hex encoded byte string, with or without a leading `0x`.
+fn deserialize_from_byte_str<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex = String::deserialize(deserializer)?;
+    let hex = hex.trim_start_matches("0x");
+    if hex.len() % 2 != 0 {
+        return Err(de::Error::custom(format!(
+            "Expected an even number of hex digits, got {}",
+            hex.len()
+        )));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| de::Error::custom(format!("Invalid hex byte string '{}'", hex)))
+        })
+        .collect()
+}
+
+/// Deserializes a value from its string representation.
+fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(de::Error::custom)
+}
+
+/// Splits strings like `"ink! 3.0.0"` into the name and the version.
+fn split_name_and_version(s: &str) -> Result<(&str, Version), String> {
+    let (name, version) = s
+        .rsplit_once(' ')
+        .ok_or_else(|| format!("Expected a name and a version, got '{}'", s))?;
+    let version =
+        Version::parse(version).map_err(|err| format!("Invalid version '{}': {}", version, err))?;
+    Ok((name, version))
+}
+
+/// Deserializes the metadata version, failing for versions which are not supported.
+fn deserialize_metadata_version<'de, D>(deserializer: D) -> Result<Version, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let version = Version::deserialize(deserializer)?;
+    let supported =
+        Version::parse(METADATA_VERSION).expect("METADATA_VERSION is a valid semver string");
+    if version.major != supported.major || version.minor != supported.minor {
+        return Err(de::Error::custom(format!(
+            "Unsupported metadata version {}, only version {} is supported",
+            version, supported
+        )));
+    }
+    Ok(version)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -648,4 +923,130 @@ mod tests {
 
         assert_eq!(json, expected);
     }
+
+    #[test]
+    fn json_with_build_info() {
+        let language = SourceLanguage::new(Language::Ink, Version::new(2, 1, 0));
+        let compiler =
+            SourceCompiler::new(Compiler::RustC, Version::parse("1.46.0-nightly").unwrap());
+        let build_info = BuildInfo {
+            cargo_contract_version: "0.3.0".to_string(),
+            wasm_opt_version: None,
+            optimization_passes: "z".to_string(),
+            build_mode: "release".to_string(),
+            keep_debug_symbols: false,
+            rustflags: "-C link-arg=--import-memory".to_string(),
+            rust_toolchain: Some("nightly-2020-06-20".to_string()),
+            cargo_lock_hash: Some(CodeHash([1u8; 32])),
+            git_commit: None,
+        };
+        let source =
+            Source::new(None, CodeHash([0u8; 32]), language, compiler).with_build_info(build_info);
+
+        let json = serde_json::to_value(&source).unwrap();
+
+        let expected = json! {
+            {
+                "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "language": "ink! 2.1.0",
+                "compiler": "rustc 1.46.0-nightly",
+                "build_info": {
+                    "cargo_contract_version": "0.3.0",
+                    "optimization_passes": "z",
+                    "build_mode": "release",
+                    "keep_debug_symbols": false,
+                    "rustflags": "-C link-arg=--import-memory",
+                    "rust_toolchain": "nightly-2020-06-20",
+                    "cargo_lock_hash": "0x0101010101010101010101010101010101010101010101010101010101010101"
+                }
+            }
+        };
+
+        assert_eq!(json, expected);
+    }
+
+    fn bundle_json() -> Value {
+        json! {
+            {
+                "metadataVersion": "0.1.0",
+                "source": {
+                    "hash": "0x0101010101010101010101010101010101010101010101010101010101010101",
+                    "language": "ink! 3.0.0-rc5",
+                    "compiler": "rustc 1.56.0-nightly",
+                    "wasm": "0x0061736d",
+                    "build_info": {
+                        "cargo_contract_version": "0.3.0",
+                        "wasm_opt_version": "wasm-opt version 101",
+                        "optimization_passes": "z",
+                        "build_mode": "release",
+                        "keep_debug_symbols": false,
+                        "rustflags": "-C link-arg=--import-memory"
+                    }
+                },
+                "contract": {
+                    "name": "incrementer",
+                    "version": "2.1.0",
+                    "authors": [
+                      "Parity Technologies <admin@parity.io>"
+                    ],
+                    "repository": "http://github.com/paritytech/ink/"
+                },
+                "user": {
+                    "some-user-provided-field": "and-its-value"
+                },
+                "spec": {},
+                "storage": {},
+                "types": []
+            }
+        }
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let json = bundle_json();
+
+        let metadata: ContractMetadata = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(serde_json::to_value(&metadata).unwrap(), json);
+    }
+
+    #[test]
+    fn language_and_compiler_strings_are_parsed() {
+        let language: SourceLanguage = "AssemblyScript 0.19.0".parse().unwrap();
+        let compiler: SourceCompiler = "solang 0.1.7".parse().unwrap();
+
+        assert_eq!(language.to_string(), "AssemblyScript 0.19.0");
+        assert_eq!(compiler.to_string(), "solang 0.1.7");
+        assert_eq!(
+            "Rust 1.0.0".parse::<SourceLanguage>().unwrap_err(),
+            "Invalid language 'Rust'"
+        );
+        assert!("rustc".parse::<SourceCompiler>().is_err());
+    }
+
+    #[test]
+    fn invalid_code_hashes_are_rejected() {
+        let mut json = bundle_json();
+        json["source"]["hash"] = json!("0x0102");
+
+        let err = serde_json::from_value::<ContractMetadata>(json).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Expected a code hash of 32 bytes, got 2 bytes"
+        );
+    }
+
+    #[test]
+    fn unsupported_metadata_version_is_rejected() {
+        let mut json = bundle_json();
+        json["metadataVersion"] = json!("1.0.0");
+
+        let err = serde_json::from_value::<ContractMetadata>(json).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Unsupported metadata version 1.0.0, only version 0.1.0 is supported"
+        );
+    }
 }
@@ -0,0 +1,174 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    contract_abi::load_contract_code,
+    metadata::{blake2_hash, parse_code_hash},
+};
+use crate::HexData;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use sp_core::{
+    crypto::{AccountId32, Pair, Ss58Codec},
+    hashing::blake2_256,
+    sr25519, H256,
+};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Computes the address of a contract before it is instantiated.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "address")]
+pub struct AddressCommand {
+    /// The account instantiating the contract, as SS58 address, hex encoded public key or secret
+    /// key URI
+    #[structopt(long)]
+    deployer: String,
+    /// The hash of the contract code
+    #[structopt(long, required_unless = "code")]
+    code_hash: Option<String>,
+    /// Path to the Wasm code or `<name>.contract` bundle to compute the code hash of
+    #[structopt(long, parse(from_os_str), conflicts_with = "code-hash")]
+    code: Option<PathBuf>,
+    /// Hex encoded salt passed to `instantiate --salt`
+    #[structopt(long, default_value = "")]
+    salt: HexData,
+    /// Export the address in JSON format.
+    #[structopt(long)]
+    pub(crate) output_json: bool,
+}
+
+impl AddressCommand {
+    pub fn exec(&self) -> Result<AddressResult> {
+        let deployer = parse_account(&self.deployer).context("Invalid deployer")?;
+        let code_hash = match (&self.code_hash, &self.code) {
+            (Some(code_hash), _) => H256(parse_code_hash(code_hash)?.0),
+            (None, Some(code)) => H256(blake2_hash(&load_contract_code(Some(code))?).0),
+            (None, None) => anyhow::bail!("Either --code-hash or --code must be supplied"),
+        };
+        let address = contract_address(&deployer, &code_hash, &self.salt.0);
+        Ok(AddressResult {
+            address: address.to_ss58check(),
+            account_id: format!("0x{}", hex::encode(AsRef::<[u8]>::as_ref(&address))),
+            deployer: deployer.to_ss58check(),
+            code_hash: format!("{:?}", code_hash),
+            salt: format!("0x{}", hex::encode(&self.salt.0)),
+        })
+    }
+}
+
+/// The predicted address of a contract, along with the inputs it was derived from.
+#[derive(serde::Serialize)]
+pub struct AddressResult {
+    /// The SS58 address of the contract.
+    pub address: String,
+    /// The hex encoded account id of the contract.
+    pub account_id: String,
+    pub deployer: String,
+    pub code_hash: String,
+    pub salt: String,
+}
+
+impl AddressResult {
+    /// Returns the result serialized as pretty-printed JSON.
+    pub fn serialize_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Renders the address along with the inputs it was derived from.
+    pub fn display(&self) -> String {
+        format!(
+            "{} {}\n\taccount id: {}\n\tdeployer:   {}\n\tcode hash:  {}\n\tsalt:       {}",
+            "Contract address:".bright_blue().bold(),
+            self.address.bold(),
+            self.account_id,
+            self.deployer,
+            self.code_hash,
+            self.salt
+        )
+    }
+}
+
+/// Derives the address of a contract the way the contracts pallet does for salted
+/// instantiations: the blake2 hash of the deployer, the code hash and the salt.
+pub(crate) fn contract_address(
+    deployer: &AccountId32,
+    code_hash: &H256,
+    salt: &[u8],
+) -> AccountId32 {
+    let input = AsRef::<[u8]>::as_ref(deployer)
+        .iter()
+        .chain(code_hash.as_bytes())
+        .chain(salt)
+        .cloned()
+        .collect::<Vec<_>>();
+    AccountId32::from(blake2_256(&input))
+}
+
+/// Parses an account from either an SS58 address, a hex encoded public key or a secret key URI.
+pub(crate) fn parse_account(input: &str) -> Result<AccountId32> {
+    if let Ok(account) = AccountId32::from_ss58check(input) {
+        return Ok(account);
+    }
+    let hex = input.trim_start_matches("0x");
+    if let Ok(bytes) = hex::decode(hex) {
+        if bytes.len() == 32 {
+            let mut raw = [0u8; 32];
+            raw.copy_from_slice(&bytes);
+            return Ok(AccountId32::from(sr25519::Public::from_raw(raw)));
+        }
+    }
+    let pair = sr25519::Pair::from_string(input, None)
+        .map_err(|_| anyhow::anyhow!("'{}' is neither a public key nor a secret URI", input))?;
+    Ok(AccountId32::from(pair.public()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{contract_address, parse_account};
+    use sp_core::{
+        crypto::{AccountId32, Ss58Codec},
+        hashing::blake2_256,
+        H256,
+    };
+
+    #[test]
+    fn addresses_depend_on_deployer_code_hash_and_salt() {
+        let alice = AccountId32::from([1; 32]);
+        let bob = AccountId32::from([2; 32]);
+        let code_hash = H256([3; 32]);
+        let address = contract_address(&alice, &code_hash, &[4]);
+
+        let mut input = vec![1; 32];
+        input.extend_from_slice(&[3; 32]);
+        input.push(4);
+        assert_eq!(address, AccountId32::from(blake2_256(&input)));
+        assert_ne!(address, contract_address(&bob, &code_hash, &[4]));
+        assert_ne!(address, contract_address(&alice, &H256([5; 32]), &[4]));
+        assert_ne!(address, contract_address(&alice, &code_hash, &[]));
+    }
+
+    #[test]
+    fn accounts_are_parsed_from_public_keys_and_secret_uris() {
+        let alice = parse_account("//Alice").unwrap();
+        let hex = hex::encode(AsRef::<[u8]>::as_ref(&alice));
+
+        assert_eq!(parse_account(&hex).unwrap(), alice);
+        assert_eq!(parse_account(&format!("0x{}", hex)).unwrap(), alice);
+        assert_eq!(parse_account(&alice.to_ss58check()).unwrap(), alice);
+        assert_ne!(parse_account("//Bob").unwrap(), alice);
+    }
+}
@@ -37,20 +37,28 @@ use std::{
 use structopt::StructOpt;
 
 /// This is the maximum number of pages available for a contract to allocate.
-const MAX_MEMORY_PAGES: u32 = 16;
+pub(crate) const MAX_MEMORY_PAGES: u32 = 16;
+
+/// Linker arguments passed via `RUSTFLAGS` when building for the wasm target.
+pub(crate) const WASM_TARGET_RUSTFLAGS: &str =
+    "-C link-arg=-zstack-size=65536 -C link-arg=--import-memory";
 
 /// Arguments to use when executing `build` or `check` commands.
 #[derive(Default)]
 pub(crate) struct ExecuteArgs {
     /// The location of the Cargo manifest (`Cargo.toml`) file to use.
     pub(crate) manifest_path: ManifestPath,
-    verbosity: Verbosity,
-    build_mode: BuildMode,
-    build_artifact: BuildArtifacts,
-    unstable_flags: UnstableFlags,
-    optimization_passes: OptimizationPasses,
-    keep_debug_symbols: bool,
-    output_type: OutputType,
+    pub(crate) verbosity: Verbosity,
+    pub(crate) build_mode: BuildMode,
+    pub(crate) build_artifact: BuildArtifacts,
+    pub(crate) unstable_flags: UnstableFlags,
+    pub(crate) optimization_passes: OptimizationPasses,
+    pub(crate) keep_debug_symbols: bool,
+    pub(crate) output_type: OutputType,
+    /// Rustup toolchain to build the contract with, the default toolchain if `None`.
+    pub(crate) toolchain: Option<String>,
+    /// `RUSTFLAGS` to build the contract with, [`WASM_TARGET_RUSTFLAGS`] if `None`.
+    pub(crate) rustflags: Option<String>,
 }
 
 /// Executes build of the smart-contract which produces a wasm binary that is ready for deploying.
@@ -168,6 +176,8 @@ impl BuildCommand {
             optimization_passes,
             keep_debug_symbols: self.keep_debug_symbols,
             output_type,
+            toolchain: None,
+            rustflags: None,
         };
 
         execute(args)
@@ -202,6 +212,8 @@ impl CheckCommand {
             optimization_passes: OptimizationPasses::Zero,
             keep_debug_symbols: false,
             output_type: OutputType::default(),
+            toolchain: None,
+            rustflags: None,
         };
 
         execute(args)
@@ -230,35 +242,10 @@ fn exec_cargo_for_wasm_target(
     build_mode: BuildMode,
     verbosity: Verbosity,
     unstable_flags: &UnstableFlags,
+    toolchain: Option<&str>,
+    rustflags: &str,
 ) -> Result<()> {
-    util::assert_channel()?;
-
-    // set linker args via RUSTFLAGS.
-    // Currently will override user defined RUSTFLAGS from .cargo/config. See https://github.com/paritytech/cargo-contract/issues/98.
-    std::env::set_var(
-        "RUSTFLAGS",
-        "-C link-arg=-zstack-size=65536 -C link-arg=--import-memory",
-    );
-
-    let cargo_build = |manifest_path: &ManifestPath| {
-        let target_dir = &crate_metadata.target_directory;
-        let target_dir = format!("--target-dir={}", target_dir.to_string_lossy());
-        let mut args = vec![
-            "--target=wasm32-unknown-unknown",
-            "-Zbuild-std",
-            "--no-default-features",
-            "--release",
-            &target_dir,
-        ];
-        if build_mode == BuildMode::Debug {
-            args.push("--features=ink_env/ink-debug");
-        } else {
-            args.push("-Zbuild-std-features=panic_immediate_abort");
-        }
-        util::invoke_cargo(command, &args, manifest_path.directory(), verbosity)?;
-
-        Ok(())
-    };
+    util::assert_channel(toolchain)?;
 
     if unstable_flags.original_manifest {
         maybe_println!(
@@ -268,7 +255,35 @@ fn exec_cargo_for_wasm_target(
             "with 'original-manifest' enabled, the contract binary may not be of optimal size."
                 .bold()
         );
-        cargo_build(&crate_metadata.manifest_path)?;
+    }
+    // set linker args via RUSTFLAGS, for this cargo invocation only.
+    // Currently will override user defined RUSTFLAGS from .cargo/config. See https://github.com/paritytech/cargo-contract/issues/98.
+    let mut env = util::toolchain_env(toolchain);
+    env.push(("RUSTFLAGS".to_string(), rustflags.to_string()));
+    with_wasm_target_manifest(crate_metadata, unstable_flags, |manifest_path| {
+        let args = cargo_args_for_wasm_target(&crate_metadata.target_directory, &[], build_mode);
+        util::invoke_cargo_with_env(command, &args, manifest_path.directory(), verbosity, &env)?;
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+/// Calls `f` with the manifest cargo is invoked on for the wasm target.
+///
+/// Unless `-Z original_manifest` is passed, this is a temporary copy of the manifest, see
+/// [`exec_cargo_for_wasm_target`].
+pub(crate) fn with_wasm_target_manifest<F>(
+    crate_metadata: &CrateMetadata,
+    unstable_flags: &UnstableFlags,
+    f: F,
+) -> Result<()>
+where
+    F: FnOnce(&ManifestPath) -> Result<()>,
+{
+    if unstable_flags.original_manifest {
+        f(&crate_metadata.manifest_path)?;
     } else {
         Workspace::new(&crate_metadata.cargo_meta, &crate_metadata.root_package.id)?
             .with_root_package_manifest(|manifest| {
@@ -277,15 +292,37 @@ fn exec_cargo_for_wasm_target(
                     .with_profile_release_defaults(Profile::default_contract_release())?;
                 Ok(())
             })?
-            .using_temp(cargo_build)?;
+            .using_temp(f)?;
     }
 
-    // clear RUSTFLAGS
-    std::env::remove_var("RUSTFLAGS");
-
     Ok(())
 }
 
+/// Returns the arguments passed to cargo by [`exec_cargo_for_wasm_target`].
+pub(crate) fn cargo_args_for_wasm_target(
+    target_dir: &Path,
+    features: &[String],
+    build_mode: BuildMode,
+) -> Vec<String> {
+    let mut args = vec![
+        "--target=wasm32-unknown-unknown".to_string(),
+        "-Zbuild-std".to_string(),
+        "--no-default-features".to_string(),
+        "--release".to_string(),
+        format!("--target-dir={}", target_dir.to_string_lossy()),
+    ];
+    let mut features = features.to_vec();
+    if build_mode == BuildMode::Debug {
+        features.push("ink_env/ink-debug".to_string());
+    } else {
+        args.push("-Zbuild-std-features=panic_immediate_abort".to_string());
+    }
+    if !features.is_empty() {
+        args.push(format!("--features={}", features.join(",")));
+    }
+    args
+}
+
 /// Ensures the wasm memory import of a given module has the maximum number of pages.
 ///
 /// Iterates over the import section, finds the memory import entry if any and adjusts the maximum
@@ -315,7 +352,7 @@ fn ensure_maximum_memory_pages(module: &mut Module, maximum_allowed_pages: u32)
         }
     } else {
         let initial = mem_ty.limits().initial();
-        *mem_ty = MemoryType::new(initial, Some(MAX_MEMORY_PAGES));
+        *mem_ty = MemoryType::new(initial, Some(maximum_allowed_pages));
     }
 
     Ok(())
@@ -356,12 +393,25 @@ fn load_module<P: AsRef<Path>>(path: P) -> Result<Module> {
 
 /// Performs required post-processing steps on the wasm artifact.
 fn post_process_wasm(crate_metadata: &CrateMetadata) -> Result<()> {
+    post_process_wasm_file(
+        &crate_metadata.original_wasm,
+        &crate_metadata.dest_wasm,
+        MAX_MEMORY_PAGES,
+    )
+}
+
+/// Performs required post-processing steps on the wasm artifact at `original_wasm`, writing the
+/// result to `dest_wasm`.
+pub(crate) fn post_process_wasm_file(
+    original_wasm: &Path,
+    dest_wasm: &Path,
+    maximum_allowed_pages: u32,
+) -> Result<()> {
     // Deserialize wasm module from a file.
-    let mut module =
-        load_module(&crate_metadata.original_wasm).context("Loading of original wasm failed")?;
+    let mut module = load_module(original_wasm).context("Loading of original wasm failed")?;
 
     strip_exports(&mut module);
-    ensure_maximum_memory_pages(&mut module, MAX_MEMORY_PAGES)?;
+    ensure_maximum_memory_pages(&mut module, maximum_allowed_pages)?;
     strip_custom_sections(&mut module);
 
     validate_wasm::validate_import_section(&module)?;
@@ -371,7 +421,7 @@ fn post_process_wasm(crate_metadata: &CrateMetadata) -> Result<()> {
         "resulting wasm size of post processing must be > 0"
     );
 
-    parity_wasm::serialize_to_file(&crate_metadata.dest_wasm, module)?;
+    parity_wasm::serialize_to_file(dest_wasm, module)?;
     Ok(())
 }
 
@@ -384,13 +434,27 @@ fn optimize_wasm(
     optimization_passes: OptimizationPasses,
     keep_debug_symbols: bool,
 ) -> Result<OptimizationResult> {
-    let mut dest_optimized = crate_metadata.dest_wasm.clone();
-    dest_optimized.set_file_name(format!(
-        "{}-opt.wasm",
-        crate_metadata.contract_artifact_name
-    ));
+    optimize_wasm_file(
+        &crate_metadata.dest_wasm,
+        &crate_metadata.contract_artifact_name,
+        optimization_passes,
+        keep_debug_symbols,
+    )
+}
+
+/// Optimizes the wasm file at `dest_wasm` in place, see [`optimize_wasm`].
+///
+/// The optimized binary is temporarily written to `<artifact_name>-opt.wasm` next to `dest_wasm`.
+pub(crate) fn optimize_wasm_file(
+    dest_wasm: &Path,
+    artifact_name: &str,
+    optimization_passes: OptimizationPasses,
+    keep_debug_symbols: bool,
+) -> Result<OptimizationResult> {
+    let mut dest_optimized = dest_wasm.to_path_buf();
+    dest_optimized.set_file_name(format!("{}-opt.wasm", artifact_name));
     let _ = do_optimization(
-        crate_metadata.dest_wasm.as_os_str(),
+        dest_wasm.as_os_str(),
         dest_optimized.as_os_str(),
         optimization_passes,
         keep_debug_symbols,
@@ -403,13 +467,13 @@ fn optimize_wasm(
         ));
     }
 
-    let original_size = metadata(&crate_metadata.dest_wasm)?.len() as f64 / 1000.0;
+    let original_size = metadata(dest_wasm)?.len() as f64 / 1000.0;
     let optimized_size = metadata(&dest_optimized)?.len() as f64 / 1000.0;
 
     // overwrite existing destination wasm file with the optimised version
-    std::fs::rename(&dest_optimized, &crate_metadata.dest_wasm)?;
+    std::fs::rename(&dest_optimized, dest_wasm)?;
     Ok(OptimizationResult {
-        dest_wasm: crate_metadata.dest_wasm.clone(),
+        dest_wasm: dest_wasm.to_path_buf(),
         original_size,
         optimized_size,
     })
@@ -583,7 +647,7 @@ fn check_wasm_opt_version_compatibility(wasm_opt_path: &Path) -> Result<()> {
 ///       are not considered incompatible.
 ///     - or if the version starts with zero (i.e. `0.y.z`) a mismatch in the minor
 ///       version is already considered incompatible.
-fn assert_compatible_ink_dependencies(
+pub(crate) fn assert_compatible_ink_dependencies(
     manifest_path: &ManifestPath,
     verbosity: Verbosity,
 ) -> Result<()> {
@@ -630,7 +694,10 @@ pub(crate) fn execute(args: ExecuteArgs) -> Result<BuildResult> {
         optimization_passes,
         keep_debug_symbols,
         output_type,
+        toolchain,
+        rustflags,
     } = args;
+    let rustflags = rustflags.unwrap_or_else(|| WASM_TARGET_RUSTFLAGS.to_string());
 
     let crate_metadata = CrateMetadata::collect(&manifest_path)?;
 
@@ -652,6 +719,8 @@ pub(crate) fn execute(args: ExecuteArgs) -> Result<BuildResult> {
             build_mode,
             verbosity,
             &unstable_flags,
+            toolchain.as_deref(),
+            &rustflags,
         )?;
 
         maybe_println!(
@@ -682,6 +751,8 @@ pub(crate) fn execute(args: ExecuteArgs) -> Result<BuildResult> {
                 BuildMode::Release,
                 verbosity,
                 &unstable_flags,
+                toolchain.as_deref(),
+                &rustflags,
             )?;
             (None, None)
         }
@@ -692,9 +763,16 @@ pub(crate) fn execute(args: ExecuteArgs) -> Result<BuildResult> {
         BuildArtifacts::All => {
             let optimization_result = build()?;
 
+            let settings = super::metadata::BuildSettings {
+                build_mode,
+                optimization_passes,
+                keep_debug_symbols,
+                rustflags: rustflags.clone(),
+            };
             let metadata_result = super::metadata::execute(
                 &crate_metadata,
                 optimization_result.dest_wasm.as_path(),
+                settings,
                 verbosity,
                 build_artifact.steps(),
                 &unstable_flags,
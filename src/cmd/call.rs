@@ -14,14 +14,148 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use codec::Decode;
+use colored::Colorize;
 use subxt::{
     balances::Balances, contracts::*, contracts_gateway::*, runtime_gateway::*, system::System,
-    ClientBuilder, ContractsTemplateRuntime,
+    ClientBuilder, ContractsTemplateRuntime, ExtrinsicSuccess,
 };
 
+use super::contract_abi::ContractAbi;
 use crate::{ExtrinsicOpts, HexData};
 
+type AccountId = <ContractsTemplateRuntime as System>::AccountId;
+
+/// Names of the events by which the contracts pallet deposits the events emitted by contracts.
+const CONTRACT_EVENTS: [&str; 2] = ["ContractExecution", "ContractEmitted"];
+
+/// Event emitted by a contract while executing an extrinsic.
+#[derive(Debug)]
+pub(crate) struct ContractEmitted {
+    /// The contract which emitted the event.
+    pub contract: AccountId,
+    /// The SCALE encoded event.
+    pub data: Vec<u8>,
+}
+
+/// Collects the events emitted by contracts while executing the extrinsic.
+fn contract_events(
+    events: &ExtrinsicSuccess<ContractsTemplateRuntime>,
+) -> Result<Vec<ContractEmitted>> {
+    events
+        .events
+        .iter()
+        .filter(|event| CONTRACT_EVENTS.contains(&event.variant.as_str()))
+        .map(|event| {
+            let (contract, data) = <(AccountId, Vec<u8>)>::decode(&mut &event.data[..]).context(
+                format!("Decoding {}::{} event", event.module, event.variant),
+            )?;
+            Ok(ContractEmitted { contract, data })
+        })
+        .collect()
+}
+
+/// Result of a contract call, with the emitted events decoded against the contract metadata.
+#[derive(serde::Serialize)]
+pub(crate) struct CallResult {
+    /// The execution stamp emitted by the gateway, for calls via a gateway.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_stamp: Option<String>,
+    /// The events emitted by contracts, in the order they were emitted.
+    pub events: Vec<CallResultEvent>,
+}
+
+/// Event emitted by a contract, decoded if the contract metadata is available.
+#[derive(serde::Serialize)]
+pub(crate) struct CallResultEvent {
+    pub contract: String,
+    /// The decoded event, if the contract metadata is available and matches the event.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<serde_json::Map<String, serde_json::Value>>,
+    /// The hex encoded event data, if the event could not be decoded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+impl CallResult {
+    /// Decodes the emitted events with the supplied ABI, falling back to their hex encoded data.
+    pub fn new(
+        execution_stamp: Option<String>,
+        emitted: Vec<ContractEmitted>,
+        abi: Option<&ContractAbi>,
+    ) -> Self {
+        let events = emitted
+            .into_iter()
+            .map(|event| {
+                let contract = event.contract.to_string();
+                let decoded = abi.map(|abi| abi.decode_event(&event.data));
+                match decoded {
+                    Some(Ok(decoded)) => CallResultEvent {
+                        contract,
+                        name: Some(decoded.name),
+                        fields: Some(decoded.fields),
+                        data: None,
+                    },
+                    decoded => {
+                        if let Some(Err(err)) = decoded {
+                            log::warn!("Failed to decode event of {}: {:#}", contract, err);
+                        }
+                        CallResultEvent {
+                            contract,
+                            name: None,
+                            fields: None,
+                            data: Some(format!("0x{}", hex::encode(&event.data))),
+                        }
+                    }
+                }
+            })
+            .collect();
+        CallResult {
+            execution_stamp,
+            events,
+        }
+    }
+
+    /// Returns the result serialized as pretty-printed JSON.
+    pub fn serialize_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Renders the execution stamp and the emitted events.
+    pub fn display(&self) -> String {
+        let mut out = String::new();
+        if let Some(execution_stamp) = &self.execution_stamp {
+            out.push_str(&format!(
+                "{} {}\n",
+                "Execution stamp:".bold(),
+                execution_stamp
+            ));
+        }
+        if self.events.is_empty() {
+            out.push_str("No contract events emitted");
+        } else {
+            out.push_str(&format!("{}", "Contract events:".bold()));
+        }
+        for event in &self.events {
+            let rendered = match (&event.name, &event.fields, &event.data) {
+                (Some(name), Some(fields), _) => {
+                    let fields = fields
+                        .iter()
+                        .map(|(name, value)| format!("{}: {}", name, value))
+                        .collect::<Vec<_>>();
+                    format!("{} {{ {} }}", name.bright_blue(), fields.join(", "))
+                }
+                (_, _, data) => format!("{} (not decoded)", data.as_deref().unwrap_or_default()),
+            };
+            out.push_str(&format!("\n  {}: {}", event.contract, rendered));
+        }
+        out
+    }
+}
+
 /// Instantiate a contract stored at the supplied code hash.
 /// Returns the account id of the instantiated contract if successful.
 ///
@@ -89,11 +223,9 @@ pub(crate) fn execute_call<'a>(
     })
 }
 
-/// Instantiate a contract stored at the supplied code hash.
-/// Returns the account id of the instantiated contract if successful.
-///
-/// Creates an extrinsic with the `Contracts::instantiate` Call, submits via RPC, then waits for
-/// the `ContractsEvent::Instantiated` event.
+/// Execute a contract via the contracts gateway.
+/// Returns the debug representation of the execution stamp, along with the events emitted by
+/// contracts during the execution.
 pub(crate) fn execute_contract_call<'a>(
     extrinsic_opts: &ExtrinsicOpts,
     requester: <ContractsTemplateRuntime as System>::AccountId,
@@ -103,8 +235,7 @@ pub(crate) fn execute_contract_call<'a>(
     value: <ContractsTemplateRuntime as Balances>::Balance,
     gas_limit: u64,
     data: HexData,
-    // ) -> Result<&'a [u8]> {
-) -> Result<()> {
+) -> Result<(String, Vec<ContractEmitted>)> {
     async_std::task::block_on(async move {
         let cli = ClientBuilder::<ContractsTemplateRuntime>::new()
             .set_url(&extrinsic_opts.url.to_string())
@@ -132,33 +263,28 @@ pub(crate) fn execute_contract_call<'a>(
                     "Failed to find a MultistepExecutePhaseSuccess event"
                 ))?;
 
-        //  /Users/macio/projects/substrate.dev/cargo-contract/target/debug/cargo-contract contract call-runtime-gateway --data 00 --suri //Alice --target //Bob --requester //Charlie
-
-        // let instantiated = events
-        //     .instantiated()?
-        //     .ok_or(anyhow::anyhow!("Failed to find Instantiated event"))?;
-        println!("{:?}", events);
+        log::debug!("gateway_contract_exec_and_watch events {:?}", events);
 
         log::info!(
             "multistep_call_and_watch execution_success_event execution_stamp {:?}",
             execution_success_event.execution_stamp
         );
-        Ok(())
+        Ok((
+            format!("{:?}", execution_success_event.execution_stamp),
+            contract_events(&events)?,
+        ))
     })
 }
 
-/// Instantiate a contract stored at the supplied code hash.
-/// Returns the account id of the instantiated contract if successful.
-///
-/// Creates an extrinsic with the `Contracts::instantiate` Call, submits via RPC, then waits for
-/// the `ContractsEvent::Instantiated` event.
+/// Call a contract via the contracts pallet.
+/// Returns the events emitted by contracts during the call.
 pub(crate) fn call_regular_contract<'a>(
     extrinsic_opts: &ExtrinsicOpts,
     contract_dest: <ContractsTemplateRuntime as System>::AccountId,
     value: <ContractsTemplateRuntime as Balances>::Balance,
     gas_limit: u64,
     data: HexData,
-) -> Result<Vec<u8>> {
+) -> Result<Vec<ContractEmitted>> {
     async_std::task::block_on(async move {
         let cli = ClientBuilder::<ContractsTemplateRuntime>::new()
             .set_url(&extrinsic_opts.url.to_string())
@@ -175,17 +301,9 @@ pub(crate) fn call_regular_contract<'a>(
                 &data.0,   // input data
             )
             .await?;
-        // ContractExecution
-        println!("regular contract call result: {:?}", events);
-        let contract_execution_event = events
-            .contract_execution()?
-            .ok_or(anyhow::anyhow!("Failed to find ContractExecutionEvent"))?;
-        println!(
-            "regular contract call result: {:?}",
-            contract_execution_event
-        );
+        log::debug!("call_and_watch events {:?}", events);
 
-        Ok(contract_execution_event.data)
+        contract_events(&events)
     })
 }
 
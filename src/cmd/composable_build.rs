@@ -14,30 +14,40 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+use super::{
+    composable_cache::{CacheEntry, CacheManifest, SharedInputs},
+    composable_wat::{WatContract, WatContractsScanner},
+};
 use crate::{
-    crate_metadata::CrateMetadata,
-    util,
-    workspace::{ManifestPath, Profile, Workspace},
-    UnstableFlags, Verbosity,
+    cmd::{
+        build,
+        metadata::{self, MetadataResult},
+    },
+    crate_metadata::{ComponentBuildConfig, ComposableScheduleMetadata, CrateMetadata},
+    maybe_println, util,
+    workspace::ManifestPath,
+    BuildArtifacts, BuildMode, OptimizationPasses, OptimizationResult, UnstableFlags, Verbosity,
 };
 use anyhow::{Context, Result};
 use colored::Colorize;
-use parity_wasm::elements::{External, MemoryType, Module, Section};
-use regex::Regex;
 use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Display, Formatter, Result as DisplayResult, Write as _},
     fs,
-    fs::metadata,
     fs::File,
-    io::{self, Write},
-    path::PathBuf,
-    process::Command,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Condvar, Mutex,
+    },
+    thread,
 };
 use wat;
 
-/// This is the maximum number of pages available for a contract to allocate.
-const MAX_MEMORY_PAGES: u32 = 16;
+/// Name of the composable manifest written to the target directory.
+const COMPOSABLE_MANIFEST_FILE: &str = "composable-manifest.json";
 
-// &format!("--target-dir={}", target_dir.to_string_lossy() + "/" + current_compose_name),
 /// Constructs a target destination path for a current compose.
 /// This is simply creating additional folder with a compose name in a target directory.
 pub fn get_compose_target_dest(compose: String, mut target_dir: PathBuf) -> PathBuf {
@@ -49,7 +59,6 @@ pub fn get_compose_target_dest(compose: String, mut target_dir: PathBuf) -> Path
 pub fn get_original_wasm_path(compose: String, crate_metadata: &CrateMetadata) -> PathBuf {
     let mut original_wasm =
         get_compose_target_dest(compose, crate_metadata.target_directory.clone()).clone();
-    // let mut original_wasm = metadata.target_directory.clone();
     original_wasm.push("wasm32-unknown-unknown");
     original_wasm.push("release");
     original_wasm.push(crate_metadata.package_name.clone());
@@ -61,480 +70,1404 @@ pub fn get_original_wasm_path(compose: String, crate_metadata: &CrateMetadata) -
 pub fn get_dest_wasm_path(compose: String, crate_metadata: &CrateMetadata) -> PathBuf {
     let mut dest_wasm =
         get_compose_target_dest(compose.clone(), crate_metadata.target_directory.clone()).clone();
-    // let mut dest_wasm = metadata.target_directory.clone();
     dest_wasm.push(compose.clone());
     dest_wasm.set_extension("wasm");
     dest_wasm
 }
 
-/// Builds the project in the specified directory, defaults to the current directory.
-///
-/// Uses [`cargo-xbuild`](https://github.com/rust-osdev/cargo-xbuild) for maximum optimization of
-/// the resulting Wasm binary.
-///
-/// # Cargo.toml optimizations
-///
-/// The original Cargo.toml will be amended to remove the `rlib` crate type in order to minimize
-/// the final Wasm binary size.
+/// Constructs a path to the metadata file of a component, next to its WASM file.
+pub fn get_dest_metadata_path(compose: String, crate_metadata: &CrateMetadata) -> PathBuf {
+    get_compose_target_dest(compose, crate_metadata.target_directory.clone())
+        .join(metadata::METADATA_FILE)
+}
+
+/// Constructs a path to the `<compose>.contract` bundle of a component, next to its WASM file.
+pub fn get_dest_bundle_path(compose: String, crate_metadata: &CrateMetadata) -> PathBuf {
+    let mut dest_bundle = get_dest_wasm_path(compose, crate_metadata);
+    dest_bundle.set_extension("contract");
+    dest_bundle
+}
+
+/// Build mode of components which neither the component nor `[package.metadata.contract]`
+/// configure.
+const DEFAULT_BUILD_MODE: BuildMode = BuildMode::Release;
+
+/// Number of `wasm-opt` passes of components which neither the component nor
+/// `[package.metadata.contract]` configure.
+const DEFAULT_OPTIMIZATION_PASSES: OptimizationPasses = OptimizationPasses::Three;
+
+/// Build settings of a single component, see [`ComponentBuildConfig`].
+#[derive(Clone, Debug, PartialEq)]
+struct ComponentSettings {
+    features: Vec<String>,
+    optimization_passes: OptimizationPasses,
+    build_mode: BuildMode,
+    max_memory_pages: u32,
+    keep_debug_symbols: bool,
+}
+
+impl ComponentSettings {
+    /// Resolves the configured settings, falling back to the defaults where unset.
+    fn new(config: ComponentBuildConfig) -> Self {
+        ComponentSettings {
+            features: config.features.unwrap_or_default(),
+            optimization_passes: config
+                .optimization_passes
+                .unwrap_or(DEFAULT_OPTIMIZATION_PASSES),
+            build_mode: config.build_mode.unwrap_or(DEFAULT_BUILD_MODE),
+            max_memory_pages: config.max_memory_pages.unwrap_or(build::MAX_MEMORY_PAGES),
+            keep_debug_symbols: config.keep_debug_symbols.unwrap_or(false),
+        }
+    }
+
+    /// Cargo features enabled for the component: the feature named after it and the extra ones.
+    fn features(&self, compose: &str) -> Vec<String> {
+        let mut features = vec![compose.to_string()];
+        features.extend(self.features.iter().cloned());
+        features
+    }
+
+    /// All settings which influence the resulting Wasm of a component.
+    fn fingerprint_args(&self, compose: &str) -> Vec<String> {
+        let mut args = vec![
+            format!("--features={}", self.features(compose).join(",")),
+            format!("--build-mode={}", self.build_mode),
+        ];
+        args.push(format!(
+            "--optimization-passes={}",
+            self.optimization_passes
+        ));
+        args.push(format!("--max-memory-pages={}", self.max_memory_pages));
+        if self.keep_debug_symbols {
+            args.push("--keep-debug-symbols".to_string());
+        }
+        args
+    }
+}
+
+/// Resolves the build settings of `compose`, the `overrides` taking precedence over the schedule.
+fn component_settings(
+    schedule: &ComposableScheduleMetadata,
+    overrides: &ComponentBuildConfig,
+    compose: &str,
+) -> ComponentSettings {
+    ComponentSettings::new(overrides.clone().or(&schedule.build_config(compose)))
+}
+
+/// Builds the component's cargo project into the component's target directory.
 ///
-/// Preferred default `[profile.release]` settings will be added if they are missing, existing
-/// user-defined settings will be preserved.
+/// Uses the same cargo invocation as `cargo contract build`, see
+/// [`build::cargo_args_for_wasm_target`], with the component's features enabled. Returns the
+/// output of cargo, which is captured since components may be built concurrently.
 ///
-/// To disable this and use the original `Cargo.toml` as is then pass the `-Z original_manifest` flag.
+/// The `RUSTFLAGS` and the code hashes of the component's dependencies are only set for this
+/// cargo invocation, see [`cargo_env`].
 fn build_cargo_project_compose(
     crate_metadata: &CrateMetadata,
-    current_compose_name: String,
-    verbosity: Option<Verbosity>,
-    unstable_options: UnstableFlags,
-) -> Result<()> {
-    util::assert_channel()?;
+    job: &BuildJob,
+    verbosity: Verbosity,
+    unstable_options: &UnstableFlags,
+) -> Result<String> {
+    let compose = job.compose.as_str();
+    let settings = &job.settings;
+    let mut env = cargo_env(&job.dependencies);
+    env.push((
+        "RUSTFLAGS".to_string(),
+        build::WASM_TARGET_RUSTFLAGS.to_string(),
+    ));
+    let target_dir =
+        get_compose_target_dest(compose.to_string(), crate_metadata.target_directory.clone());
+    let args = build::cargo_args_for_wasm_target(
+        &target_dir,
+        &settings.features(compose),
+        settings.build_mode,
+    );
+    let mut output = String::new();
+    build::with_wasm_target_manifest(crate_metadata, unstable_options, |manifest_path| {
+        output = util::invoke_cargo_captured(
+            "build",
+            &args,
+            manifest_path.directory(),
+            verbosity,
+            &env,
+        )?
+        .stderr;
+        Ok(())
+    })?;
+    Ok(output)
+}
 
-    // set RUSTFLAGS, read from environment var by cargo-xbuild
-    std::env::set_var(
-        "RUSTFLAGS",
-        "-C link-arg=-z -C link-arg=stack-size=65536 -C link-arg=--import-memory",
+/// Load a given wasm module read from a contract file and save a compiled wasm byte code as a file.
+///
+/// File will be stored at the same destination path as it would be after regular compilation with cargo.
+fn compile_wat_to_wasm(
+    compose_name: String,
+    crate_metadata: &CrateMetadata,
+    wat_contract: &WatContract,
+) -> Result<()> {
+    let dest_wat_dir = get_compose_target_dest(
+        compose_name.clone(),
+        crate_metadata.target_directory.clone(),
     );
+    fs::create_dir_all(dest_wat_dir).context(format!(
+        "Error when creating a new directory for WAT contract at ./target for component: {}",
+        compose_name
+    ))?;
+    // Keep a copy of the Wasm text format next to the compiled contract.
+    if !wat_contract.is_binary() {
+        let mut dest_wat_path = get_dest_wasm_path(compose_name.clone(), crate_metadata);
+        dest_wat_path.set_extension("wat");
+        fs::write(&dest_wat_path, &wat_contract.code).context(format!(
+            "Error when saving WAT contract as file at ./target for component: {}",
+            compose_name
+        ))?;
+    }
 
-    let verbosity = verbosity.map(|v| match v {
-        Verbosity::Default => xargo_lib::Verbosity::Verbose,
-        Verbosity::Verbose => xargo_lib::Verbosity::Verbose,
-        Verbosity::Quiet => xargo_lib::Verbosity::Quiet,
-    });
+    // Binary contracts are passed through unchanged by the parser.
+    let wasm_bytes = wat::parse_bytes(&wat_contract.code).context(format!(
+        "Error during WAT contract compilation for component: {} ({})",
+        compose_name, wat_contract
+    ))?;
 
-    let xbuild = |manifest_path: &ManifestPath| {
-        let manifest_path = Some(manifest_path);
-        let target = Some("wasm32-unknown-unknown");
-        let target_dir = &crate_metadata.cargo_meta.target_directory;
-
-        let other_args = [
-            "--no-default-features",
-            &format!("--features={}", current_compose_name),
-            "--release",
-            &format!(
-                "--target-dir={}",
-                get_compose_target_dest(current_compose_name, target_dir.into())
-                    .to_string_lossy()
-            ),
-        ];
-        let args = xargo_lib::Args::new(target, manifest_path, verbosity, &other_args)
-            .map_err(|e| anyhow::anyhow!("{}", e))
-            .context("Creating xargo args")?;
-
-        let config = xargo_lib::Config {
-            sysroot_path: target_dir.join("sysroot").into(),
-            memcpy: false,
-            panic_immediate_abort: true,
-        };
-        let exit_status = xargo_lib::build(args, "build", Some(config))
-            .map_err(|e| anyhow::anyhow!("{}", e))
-            .context("Building with xbuild")?;
-        if !exit_status.success() {
-            anyhow::bail!("xbuild failed with status {}", exit_status)
+    let mut file = File::create(get_dest_wasm_path(compose_name.clone(), crate_metadata))?;
+    // Write a slice of WASM bytes to the file
+    file.write_all(&wasm_bytes).context(format!(
+        "Error when saving WASM contract as file at ./target for component: {}",
+        compose_name
+    ))?;
+    Ok(())
+}
+
+/// Status of a single component after a composable build.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Serialize)]
+pub enum ComponentBuildStatus {
+    /// The component was built successfully.
+    Success,
+    /// The component is up to date, its inputs did not change since it was last built.
+    Cached,
+    /// Building the component failed.
+    Failed,
+    /// The component was not built, because the build was aborted after another component failed,
+    /// or because one of its dependencies could not be built.
+    Skipped,
+}
+
+impl Display for ComponentBuildStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
+        match self {
+            Self::Success => write!(f, "OK"),
+            Self::Cached => write!(f, "CACHED"),
+            Self::Failed => write!(f, "FAILED"),
+            Self::Skipped => write!(f, "SKIPPED"),
         }
-        Ok(())
-    };
+    }
+}
 
-    if unstable_options.original_manifest {
-        println!(
-            "{} {}",
-            "warning:".yellow().bold(),
-            "with 'original-manifest' enabled, the contract binary may not be of optimal size."
-                .bold()
-        );
-        xbuild(&crate_metadata.manifest_path)?;
-    } else {
-        Workspace::new(&crate_metadata.cargo_meta, &crate_metadata.root_package.id)?
-            .with_root_package_manifest(|manifest| {
-                manifest
-                    .with_removed_crate_type("rlib")?
-                    .with_profile_release_defaults(Profile::default_contract_release())?;
-                Ok(())
-            })?
-            .using_temp(xbuild)?;
+/// Result of building a single component of the composable schedule.
+#[derive(serde::Serialize)]
+pub struct ComponentBuildResult {
+    /// Name of the component, as listed in the composable schedule.
+    pub compose: String,
+    /// Whether the component was built.
+    pub status: ComponentBuildStatus,
+    /// Path to the resulting Wasm file.
+    pub dest_wasm: Option<PathBuf>,
+    /// Hex encoded hash of the resulting Wasm code.
+    pub code_hash: Option<String>,
+    /// The Wasm size before optimizations, if `wasm-opt` was run.
+    pub original_size: Option<f64>,
+    /// The Wasm size after optimizations, if `wasm-opt` was run.
+    pub optimized_size: Option<f64>,
+    /// Paths to the metadata and the bundle, if they were generated.
+    pub metadata_result: Option<MetadataResult>,
+    /// The error which made the component build fail.
+    pub error: Option<String>,
+}
+
+impl ComponentBuildResult {
+    fn new(compose: String, result: Result<ComponentArtifacts>) -> Self {
+        match result {
+            Ok(artifacts) => ComponentBuildResult {
+                compose,
+                status: if artifacts.cached {
+                    ComponentBuildStatus::Cached
+                } else {
+                    ComponentBuildStatus::Success
+                },
+                dest_wasm: Some(artifacts.dest_wasm),
+                code_hash: Some(artifacts.code_hash),
+                original_size: artifacts.optimization.as_ref().map(|o| o.original_size),
+                optimized_size: artifacts.optimization.as_ref().map(|o| o.optimized_size),
+                metadata_result: artifacts.metadata_result,
+                error: None,
+            },
+            Err(err) => ComponentBuildResult {
+                compose,
+                status: ComponentBuildStatus::Failed,
+                dest_wasm: None,
+                code_hash: None,
+                original_size: None,
+                optimized_size: None,
+                metadata_result: None,
+                error: Some(format!("{:#}", err)),
+            },
+        }
     }
 
-    // clear RUSTFLAGS
-    std::env::remove_var("RUSTFLAGS");
+    fn skipped(compose: String) -> Self {
+        ComponentBuildResult {
+            compose,
+            status: ComponentBuildStatus::Skipped,
+            dest_wasm: None,
+            code_hash: None,
+            original_size: None,
+            optimized_size: None,
+            metadata_result: None,
+            error: None,
+        }
+    }
+}
 
-    Ok(())
+/// Result of building all components of the composable schedule.
+#[derive(serde::Serialize)]
+pub struct ComposableBuildResult {
+    /// Path to the directory where the components are written to.
+    pub target_directory: PathBuf,
+    /// Path to the composable manifest, listing all successfully built components.
+    pub dest_manifest: PathBuf,
+    /// Results of the individual components, in execution order.
+    pub components: Vec<ComponentBuildResult>,
 }
 
-/// Ensures the wasm memory import of a given module has the maximum number of pages.
-///
-/// Iterates over the import section, finds the memory import entry if any and adjusts the maximum
-/// limit.
-fn ensure_maximum_memory_pages(module: &mut Module, maximum_allowed_pages: u32) -> Result<()> {
-    let mem_ty = module
-        .import_section_mut()
-        .and_then(|section| {
-            section
-                .entries_mut()
-                .iter_mut()
-                .find_map(|entry| match entry.external_mut() {
-                    External::Memory(ref mut mem_ty) => Some(mem_ty),
-                    _ => None,
-                })
+impl ComposableBuildResult {
+    /// Returns `true` if every component was built successfully or is up to date.
+    pub fn is_success(&self) -> bool {
+        self.components.iter().all(|c| {
+            matches!(
+                c.status,
+                ComponentBuildStatus::Success | ComponentBuildStatus::Cached
+            )
         })
-        .context("Memory import is not found. Is --import-memory specified in the linker args")?;
-
-    if let Some(requested_maximum) = mem_ty.limits().maximum() {
-        // The module already has maximum, check if it is within the limit bail out.
-        if requested_maximum > maximum_allowed_pages {
-            anyhow::bail!(
-                "The wasm module requires {} pages. The maximum allowed number of pages is {}",
-                requested_maximum,
-                maximum_allowed_pages,
-            );
+    }
+
+    /// Returns the number of components which failed to build.
+    pub fn failed(&self) -> usize {
+        self.components
+            .iter()
+            .filter(|c| c.status == ComponentBuildStatus::Failed)
+            .count()
+    }
+
+    /// Returns the result serialized as pretty-printed JSON.
+    pub fn serialize_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Renders a table listing which components were built successfully and which were not.
+    pub fn display(&self) -> String {
+        let width = self
+            .components
+            .iter()
+            .map(|c| c.compose.len())
+            .max()
+            .unwrap_or_default()
+            .max("Component".len());
+        let mut out = format!(
+            "\n {:<width$}  {:<7}  {:>9}  {}\n",
+            "Component".bold(),
+            "Status".bold(),
+            "Size".bold(),
+            "Wasm".bold(),
+            width = width
+        );
+        for component in &self.components {
+            let status = match component.status {
+                ComponentBuildStatus::Success | ComponentBuildStatus::Cached => {
+                    component.status.to_string().bright_green()
+                }
+                ComponentBuildStatus::Failed => component.status.to_string().bright_red(),
+                ComponentBuildStatus::Skipped => component.status.to_string().yellow(),
+            };
+            let size = component
+                .optimized_size
+                .map(|size| format!("{:.1}K", size))
+                .unwrap_or_default();
+            let dest_wasm = component
+                .dest_wasm
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default();
+            out.push_str(&format!(
+                " {:<width$}  {:<7}  {:>9}  {}\n",
+                component.compose,
+                status.bold(),
+                size,
+                dest_wasm,
+                width = width
+            ));
         }
-    } else {
-        let initial = mem_ty.limits().initial();
-        *mem_ty = MemoryType::new(initial, Some(MAX_MEMORY_PAGES));
+        out.push_str(&format!(
+            "\nYour composable contract(s) can be found in the following directory:\n{}",
+            self.target_directory.display().to_string().bold()
+        ));
+        out.push_str(&format!(
+            "\n\nThe composable manifest listing their code hashes was written to:\n{}",
+            self.dest_manifest.display().to_string().bold()
+        ));
+        out
     }
+}
 
-    Ok(())
+/// How a component of the composable schedule is built.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentKind {
+    /// The component is compiled from Wasm text format, or copied if it is already binary.
+    Wat,
+    /// The component is built from the contract crate with cargo.
+    Cargo,
 }
 
-/// Strips all custom sections.
-///
-/// Presently all custom sections are not required so they can be stripped safely.
-fn strip_custom_sections(module: &mut Module) {
-    module.sections_mut().retain(|section| match section {
-        Section::Custom(_) => false,
-        Section::Name(_) => false,
-        Section::Reloc(_) => false,
-        _ => true,
-    });
+impl Display for ComponentKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
+        match self {
+            Self::Wat => write!(f, "wat"),
+            Self::Cargo => write!(f, "cargo"),
+        }
+    }
 }
 
-/// Performs required post-processing steps on the wasm artifact.
-fn post_process_wasm_compose(crate_metadata: &CrateMetadata, compose: String) -> Result<()> {
-    // Deserialize wasm module from a file.
-    let mut module =
-        parity_wasm::deserialize_file(get_original_wasm_path(compose.clone(), crate_metadata))
-            .context(format!(
-                "Loading original wasm file '{}'",
-                crate_metadata.original_wasm.display()
-            ))?;
+/// Resolved build plan of a single component, see [`plan`].
+#[derive(serde::Serialize)]
+pub struct ComponentBuildPlan {
+    /// Name of the component, as listed in the composable schedule.
+    pub compose: String,
+    pub kind: ComponentKind,
+    /// Components which are built before this component.
+    pub dependencies: Vec<String>,
+    /// The file the Wasm text format component was found in.
+    pub source: Option<String>,
+    /// Arguments passed to `cargo build`, empty for Wasm text format components.
+    pub cargo_args: Vec<String>,
+    /// Directory the component is built into.
+    pub target_directory: PathBuf,
+    /// Path to the resulting Wasm file.
+    pub dest_wasm: PathBuf,
+    /// Path to the `<compose>.contract` bundle, if it is generated.
+    pub dest_bundle: Option<PathBuf>,
+}
+
+/// Resolved build plan of the composable schedule, as printed by `composable-build --dry-run`.
+#[derive(serde::Serialize)]
+pub struct ComposableBuildPlan {
+    /// `RUSTFLAGS` set for all cargo builds.
+    pub rustflags: String,
+    /// Plans of the individual components, in execution order.
+    pub components: Vec<ComponentBuildPlan>,
+}
 
-    // Perform optimization.
-    //
-    // In practice only tree-shaking is performed, i.e transitively removing all symbols that are
-    // NOT used by the specified entrypoints.
-    if pwasm_utils::optimize(&mut module, ["call", "deploy"].to_vec()).is_err() {
-        anyhow::bail!("Optimizer failed");
+impl ComposableBuildPlan {
+    /// Returns the plan serialized as pretty-printed JSON.
+    pub fn serialize_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
     }
-    ensure_maximum_memory_pages(&mut module, MAX_MEMORY_PAGES)?;
-    strip_custom_sections(&mut module);
 
-    parity_wasm::serialize_to_file(get_dest_wasm_path(compose.clone(), crate_metadata), module)?;
-    Ok(())
+    /// Renders the steps of every component, in execution order.
+    pub fn display(&self) -> String {
+        let mut out = format!(
+            "{}\n",
+            "Composable build plan (dry run), nothing is built"
+                .bright_blue()
+                .bold()
+        );
+        for (index, component) in self.components.iter().enumerate() {
+            out.push_str(&format!(
+                "\n {} {} ({})\n",
+                format!("[{}/{}]", index + 1, self.components.len()).bold(),
+                component.compose.bold(),
+                component.kind
+            ));
+            if !component.dependencies.is_empty() {
+                out.push_str(&format!(
+                    "   depends on: {}\n",
+                    component.dependencies.join(", ")
+                ));
+            }
+            if let Some(source) = &component.source {
+                out.push_str(&format!("   source:     {}\n", source));
+            }
+            if !component.cargo_args.is_empty() {
+                out.push_str(&format!(
+                    "   cargo:      RUSTFLAGS=\"{}\" cargo build {}\n",
+                    self.rustflags,
+                    component.cargo_args.join(" ")
+                ));
+            }
+            out.push_str(&format!(
+                "   target dir: {}\n   wasm:       {}\n",
+                component.target_directory.display(),
+                component.dest_wasm.display()
+            ));
+            if let Some(dest_bundle) = &component.dest_bundle {
+                out.push_str(&format!("   bundle:     {}\n", dest_bundle.display()));
+            }
+        }
+        out
+    }
+}
+
+/// Manifest listing the code hashes and artifacts of all built components.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ComposableManifest {
+    components: Vec<ComposableManifestEntry>,
 }
 
-/// Attempts to perform optional wasm optimization using `wasm-opt`.
+/// Entry of a component in the composable manifest.
 ///
-/// The intention is to reduce the size of bloated wasm binaries as a result of missing
-/// optimizations (or bugs?) between Rust and Wasm.
+/// All paths are relative to the target directory.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ComposableManifestEntry {
+    name: String,
+    code_hash: String,
+    wasm: PathBuf,
+    metadata: Option<PathBuf>,
+    bundle: Option<PathBuf>,
+}
+
+/// Writes the composable manifest, listing all components which were built successfully or are
+/// up to date, together with their code hashes and artifacts.
 ///
-/// This step depends on the `wasm-opt` tool being installed. If it is not the build will still
-/// succeed, and the user will be encouraged to install it for further optimizations.
-fn optimize_wasm_compose(crate_metadata: &CrateMetadata, compose: String) -> Result<()> {
-    // check `wasm-opt` installed
-    if which::which("wasm-opt").is_err() {
-        println!(
-            "{}",
-            "wasm-opt is not installed. Install this tool on your system in order to \n\
-             reduce the size of your contract's Wasm binary. \n\
-             See https://github.com/WebAssembly/binaryen#tools"
-                .bright_yellow()
-        );
-        return Ok(());
+/// Entries of components which were not built this time, e.g. because only some components were
+/// selected, are retained from the previous manifest. Entries are ordered like `names`, entries of
+/// components which are no longer part of the schedule are dropped.
+fn write_composable_manifest(
+    target_directory: &Path,
+    names: &[&str],
+    components: &[ComponentBuildResult],
+) -> Result<PathBuf> {
+    let dest_manifest = target_directory.join(COMPOSABLE_MANIFEST_FILE);
+    let mut entries = fs::read(&dest_manifest)
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<ComposableManifest>(&contents).ok())
+        .map(|manifest| manifest.components)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|entry| (entry.name.clone(), entry))
+        .collect::<HashMap<_, _>>();
+
+    let relative = |path: &PathBuf| {
+        path.strip_prefix(target_directory)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| path.clone())
+    };
+    for component in components {
+        match (&component.code_hash, &component.dest_wasm) {
+            (Some(code_hash), Some(dest_wasm)) => {
+                let entry = ComposableManifestEntry {
+                    name: component.compose.clone(),
+                    code_hash: code_hash.clone(),
+                    wasm: relative(dest_wasm),
+                    metadata: component
+                        .metadata_result
+                        .as_ref()
+                        .map(|m| relative(&m.dest_metadata)),
+                    bundle: component
+                        .metadata_result
+                        .as_ref()
+                        .map(|m| relative(&m.dest_bundle)),
+                };
+                entries.insert(component.compose.clone(), entry);
+            }
+            _ if component.status == ComponentBuildStatus::Failed => {
+                entries.remove(&component.compose);
+            }
+            _ => (),
+        }
     }
-    let dest_wasm = get_dest_wasm_path(compose.clone(), crate_metadata);
-    let mut optimized = dest_wasm.clone();
-    optimized.set_file_name(format!("{}-opt.wasm", compose.clone()));
-
-    let output = Command::new("wasm-opt")
-        .arg(dest_wasm.clone().as_os_str())
-        .arg("-O3") // execute -O3 optimization passes (spends potentially a lot of time optimizing)
-        .arg("-o")
-        .arg(optimized.as_os_str())
-        .output()?;
-
-    if !output.status.success() {
-        // Dump the output streams produced by wasm-opt into the stdout/stderr.
-        io::stdout().write_all(&output.stdout)?;
-        io::stderr().write_all(&output.stderr)?;
-        anyhow::bail!("wasm-opt optimization failed");
-    }
-
-    let original_size = metadata(&dest_wasm)?.len() as f64 / 1000.0;
-    let optimized_size = metadata(&optimized)?.len() as f64 / 1000.0;
-    println!(
-        " Original wasm size: {:.1}K, Optimized: {:.1}K",
-        original_size, optimized_size
-    );
 
-    // overwrite existing destination wasm file with the optimised version
-    std::fs::rename(&optimized, &dest_wasm)?;
-    Ok(())
+    let manifest = ComposableManifest {
+        components: names
+            .iter()
+            .filter_map(|name| entries.remove(*name))
+            .collect(),
+    };
+    let contents = serde_json::to_string_pretty(&manifest)?;
+    fs::write(&dest_manifest, contents).context(format!(
+        "Writing composable manifest to '{}'",
+        dest_manifest.display()
+    ))?;
+    Ok(dest_manifest)
+}
+
+/// Artifacts produced by a successful component build.
+struct ComponentArtifacts {
+    dest_wasm: PathBuf,
+    code_hash: String,
+    optimization: Option<OptimizationResult>,
+    metadata_result: Option<MetadataResult>,
+    /// `true` if the build was skipped, because the component is up to date.
+    cached: bool,
 }
 
-/// Scanner for Wasm text format contracts.
-/// Developers can write their contract directly in WAT format by following the following rules:
-/// Assign contract as a string constant. Name of the contract must specifically match the composable name (either upper or lowe case).
-/// Contract name and code are looked up by a regexp, therefore a contract must be placed always between: `r#` and `"#;`
-/// static EXAMPLE_WAT_CONTRACT: &str = r#"
-///         (module
-///            (func (export "call"))
-///            (func (export "deploy"))
-///         )
-///         "#;
-/// Out of the above example the scanner will recognize an entry: WatContract { name: "example", code: "(module (func..."deploy")) )" }
-#[derive(Debug)]
-pub struct WatContractsScanner {
-    contracts: Vec<WatContract>,
-}
-
-/// Entry of a smart contract as Wasm text format.
-#[derive(Debug, Clone)]
-pub struct WatContract {
-    code: &'static str,
-    name: &'static str,
-}
-
-impl WatContractsScanner {
-    fn new_empty() -> WatContractsScanner {
-        WatContractsScanner { contracts: vec![] }
-    }
-
-    fn scan_from_text(&mut self, text: &'static str) -> bool {
-        for caps in Regex::new(
-            r"(?P<compose_name>[\w]+)_WAT_CONTRACT: &str = r#(?P<wat_contract>[\s\S]+)#;",
-        )
-        .unwrap()
-        .captures_iter(text)
-        {
-            self.contracts.push(WatContract {
-                name: caps
-                    .name("compose_name")
-                    .map(|r| Box::leak(r.as_str().to_lowercase().into_boxed_str()))
-                    .unwrap(),
-                code: caps.name("wat_contract").map(|r| r.as_str()).unwrap(),
+/// A component handed out to a build worker.
+struct BuildJob {
+    /// Position of the component in the execution order.
+    index: usize,
+    compose: String,
+    settings: ComponentSettings,
+    /// Names and code hashes of the components this component depends on.
+    dependencies: Vec<(String, String)>,
+    /// `true` if one of the dependencies could not be built, so neither can this component.
+    blocked: bool,
+}
+
+/// Hands out the components to the build workers in execution order, each of them only after all
+/// of its dependencies are done.
+struct BuildQueue {
+    state: Mutex<BuildQueueState>,
+    done: Condvar,
+}
+
+/// A component which was not handed out to a build worker yet.
+struct PendingComponent {
+    index: usize,
+    compose: String,
+    settings: ComponentSettings,
+    dependencies: Vec<String>,
+}
+
+struct BuildQueueState {
+    /// Components which were not handed out yet.
+    pending: VecDeque<PendingComponent>,
+    /// Components which are done, with their code hash if they were built successfully.
+    finished: HashMap<String, Option<String>>,
+}
+
+impl BuildQueue {
+    fn new(
+        schedule: &ComposableScheduleMetadata,
+        overrides: &ComponentBuildConfig,
+        order: Vec<String>,
+    ) -> Self {
+        let pending = order
+            .into_iter()
+            .enumerate()
+            .map(|(index, compose)| PendingComponent {
+                index,
+                settings: component_settings(schedule, overrides, &compose),
+                dependencies: schedule.dependencies_of(&compose).to_vec(),
+                compose,
+            })
+            .collect();
+        BuildQueue {
+            state: Mutex::new(BuildQueueState {
+                pending,
+                finished: HashMap::new(),
+            }),
+            done: Condvar::new(),
+        }
+    }
+
+    /// Returns the next component whose dependencies are done, blocking until there is one.
+    ///
+    /// Returns `None` once all components were handed out.
+    fn next(&self) -> Option<BuildJob> {
+        let mut state = self.state.lock().expect("queue lock poisoned");
+        loop {
+            if state.pending.is_empty() {
+                return None;
+            }
+            let finished = &state.finished;
+            let ready = state.pending.iter().position(|pending| {
+                pending
+                    .dependencies
+                    .iter()
+                    .all(|d| finished.contains_key(d))
             });
+            if let Some(position) = ready {
+                let pending = state
+                    .pending
+                    .remove(position)
+                    .expect("position was found in pending; qed");
+                let code_hashes = pending
+                    .dependencies
+                    .into_iter()
+                    .map(|d| {
+                        let code_hash = state.finished[&d].clone();
+                        (d, code_hash)
+                    })
+                    .collect::<Vec<_>>();
+                return Some(BuildJob {
+                    index: pending.index,
+                    compose: pending.compose,
+                    settings: pending.settings,
+                    blocked: code_hashes.iter().any(|(_, code_hash)| code_hash.is_none()),
+                    dependencies: code_hashes
+                        .into_iter()
+                        .filter_map(|(d, code_hash)| code_hash.map(|h| (d, h)))
+                        .collect(),
+                });
+            }
+            state = self.done.wait(state).expect("queue lock poisoned");
         }
-        !self.contracts.is_empty()
     }
 
-    fn find_by_name(&mut self, name: String) -> Option<&WatContract> {
-        let r = self.contracts.iter().find(|&c| c.name == name);
-        // println!("find_by_name res {:?} {:?}", name, r);
-        r
+    /// Marks the component as done, handing its code hash to the components depending on it.
+    fn finish(&self, compose: &str, code_hash: Option<String>) {
+        let mut state = self.state.lock().expect("queue lock poisoned");
+        state.finished.insert(compose.to_string(), code_hash);
+        self.done.notify_all();
     }
 }
-/// Load a given wasm module read from a contract file and save a compiled wasm byte code as a file.
-///
-/// File will be stored at the same destination path as it would be after regular compilation with xbuild.
-fn compile_wat_to_wasm(
-    compose_name: String,
-    crate_metadata: &CrateMetadata,
-    wat_contract: &WatContract,
-) -> Result<()> {
-    let dest_wat_dir = get_compose_target_dest(
-        compose_name.clone(),
-        crate_metadata.target_directory.clone(),
-    );
-    fs::create_dir_all(dest_wat_dir.clone()).map_err(|e| {
-        println!(
-            "{} {} {}",
-            "Error when creating a new directory for WAT contract at ./target for component: "
-                .bright_red()
-                .bold(),
-            compose_name,
-            e.to_string()
-        );
-        e
-    })?;
-    let mut dest_wat_path = get_dest_wasm_path(compose_name.clone(), crate_metadata);
-    dest_wat_path.set_extension("wat");
-    let mut file = File::create(dest_wat_path.clone()).map_err(|e| {
-        println!(
-            "{} {} {}",
-            "Error when creating an empty file for WAT contract at ./target for component: "
-                .bright_red()
-                .bold(),
-            compose_name,
-            e.to_string()
-        );
-        e
-    })?;
-    let code_bytes = wat_contract.code.as_bytes();
-    // During regexp search there are additional \" at the beginning and EOF. Strip them out as they're fail the compilation.
-    file.write_all(&code_bytes[1..code_bytes.len() - 1])
-        .map_err(|e| {
-            println!(
-                "{} {} {}",
-                "Error when saving WAT contract as file at ./target for component: "
-                    .bright_red()
-                    .bold(),
-                compose_name,
-                e.to_string()
-            );
-            e
-        })?;
-
-    // After the .wat file is saved, read it again with wat2wasm compiler / parser
-    let wasm_bytes = wat::parse_file(dest_wat_path.clone()).map_err(|e| {
-        println!(
-            "{} {} {}",
-            "Error during WAT contract compilation for component: "
-                .bright_red()
-                .bold(),
-            compose_name,
-            e.to_string()
-        );
-        e
-    })?;
 
-    let mut file = File::create(get_dest_wasm_path(compose_name.clone(), crate_metadata))?;
-    // Write a slice of WASM bytes to the file
-    file.write_all(&wasm_bytes).map_err(|e| {
-        println!(
-            "{} {} {}",
-            "Error when saving WASM contract as file at ./target for component: "
-                .bright_red()
-                .bold(),
-            compose_name,
-            e.to_string()
-        );
-        e
-    })?;
-    Ok(())
+/// Outcome of building a single component, together with the output it produced.
+struct ComponentOutcome {
+    /// Position of the component in the execution order.
+    index: usize,
+    /// Fingerprint of the component's build inputs.
+    fingerprint: String,
+    /// Buffered output of the component build, printed once the component is done.
+    log: String,
+    result: ComponentBuildResult,
 }
-/// Reads contract file (lib.rs) as a text.
-fn read_contracts_file_as_text(crate_metadata: &CrateMetadata) -> String {
-    let target_dir = &crate_metadata.cargo_meta.target_directory;
-    let mut composable_contract_source_path: PathBuf = target_dir.into();
-    composable_contract_source_path.pop();
-    composable_contract_source_path.push("lib");
-    composable_contract_source_path.set_extension("rs");
 
-    fs::read_to_string(composable_contract_source_path)
-        .expect("Something went wrong reading the composable contracts file")
+/// Arguments to use when executing `composable-build`.
+pub(crate) struct ExecuteArgs {
+    /// The location of the Cargo manifest (`Cargo.toml`) file to use.
+    pub(crate) manifest_path: ManifestPath,
+    pub(crate) verbosity: Verbosity,
+    /// Which build artifacts to generate for the ink! components.
+    pub(crate) build_artifact: BuildArtifacts,
+    pub(crate) unstable_flags: UnstableFlags,
+    /// Build settings passed on the command line, taking precedence over the schedule.
+    pub(crate) overrides: ComponentBuildConfig,
+    /// Number of components to build concurrently.
+    pub(crate) jobs: usize,
+    /// Continue building the remaining components after a component failed to build.
+    pub(crate) keep_going: bool,
+    /// Rebuild all components, even if they are up to date.
+    pub(crate) force: bool,
+    /// Components to build, along with their dependencies. All components are built if empty.
+    pub(crate) compose: Vec<String>,
+}
+
+/// Options shared by the builds of all components.
+struct BuildOptions {
+    /// Verbosity of the cargo invocations.
+    verbosity: Verbosity,
+    unstable_flags: UnstableFlags,
+    build_artifact: BuildArtifacts,
 }
 
 /// Executes build of the smart-contract which produces a wasm binary that is ready for deploying.
 ///
 /// It does so by invoking `cargo build` and then post processing the final binary.
 ///
-/// # Note
+/// Components are built after the components they depend on, see
+/// [`ComposableScheduleMetadata::execution_order`]. The code hash of each built component is
+/// passed to the builds of its dependents via an environment variable, see [`code_hash_env_var`].
+/// Components whose dependencies could not be built are reported as skipped.
 ///
-/// Collects the contract crate's metadata using the supplied manifest (`Cargo.toml`) path. Use
-/// [`execute_build_with_metadata`] if an instance is already available.
-pub(crate) fn execute(
-    manifest_path: &ManifestPath,
-    verbosity: Option<Verbosity>,
-    unstable_options: UnstableFlags,
-) -> Result<PathBuf> {
-    let crate_metadata = CrateMetadata::collect(manifest_path)?;
+/// Up to `jobs` components are built concurrently. Each component is built into its own target
+/// directory (see [`get_compose_target_dest`]), its output is buffered and printed in
+/// execution order.
+///
+/// Unless `keep_going` is set, no further components are started once a component failed to
+/// build. Components which were not started are reported as skipped.
+///
+/// Components whose build inputs did not change since their last successful build are not
+/// built again, unless `force` is set. See [`composable_cache`](super::composable_cache).
+///
+/// The `overrides`, i.e. the settings passed on the command line, take precedence over the
+/// build settings of the individual components in the schedule.
+///
+/// Unless only the code is requested, the metadata and a `<compose>.contract` bundle are
+/// generated next to the Wasm of every ink! component. Afterwards a composable manifest listing
+/// the code hashes and artifacts of all built components is written to the target directory.
+pub(crate) fn execute(args: ExecuteArgs) -> Result<ComposableBuildResult> {
+    let ExecuteArgs {
+        manifest_path,
+        verbosity,
+        build_artifact,
+        unstable_flags,
+        overrides,
+        jobs,
+        keep_going,
+        force,
+        compose,
+    } = args;
 
-    let composable_schedule = crate_metadata.clone().t3rn_composable_schedule
-        .expect("Failed to read composable metadata from JSON using serde. Make sure your Cargo.toml follows the composable metadata format");
+    if jobs == 0 {
+        anyhow::bail!("The number of jobs must be at least 1");
+    }
+
+    let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+
+    let composable_schedule = crate_metadata.composable_schedule()?;
+
+    if composable_schedule.composables.is_empty() {
+        anyhow::bail!("Empty composable t3rn contracts schedule. Didn't compile anything.");
+    }
 
-    let mut compose_dest_path = Err(anyhow::anyhow!(
-        "Empty composable t3rn contracts schedule. Didn't compile anything."
-    ));
     // Scan for Wasm text format components.
-    let contents = read_contracts_file_as_text(&crate_metadata);
-    let mut contracts_scanner = WatContractsScanner::new_empty();
-    contracts_scanner.scan_from_text(Box::leak(contents.into_boxed_str()));
-
-    for compose in composable_schedule.composables.clone() {
-        compose_dest_path = execute_with_metadata_composable(
-            &crate_metadata,
-            &mut contracts_scanner,
-            compose,
+    let contracts_scanner = WatContractsScanner::discover(&crate_metadata, &composable_schedule)?;
+
+    let order = composable_schedule.execution_order_of(&compose)?;
+    let cargo_components = order
+        .iter()
+        .filter(|compose| contracts_scanner.find_by_name(compose).is_none())
+        .map(|compose| component_settings(&composable_schedule, &overrides, compose))
+        .collect::<Vec<_>>();
+    if !cargo_components.is_empty() {
+        build::assert_compatible_ink_dependencies(&manifest_path, verbosity)?;
+    }
+    if cargo_components
+        .iter()
+        .any(|settings| settings.build_mode == BuildMode::Debug)
+    {
+        build::assert_debug_mode_supported(&crate_metadata.ink_version)?;
+    }
+    if unstable_flags.original_manifest {
+        maybe_println!(
             verbosity,
-            unstable_options.clone(),
+            "{} {}",
+            "warning:".yellow().bold(),
+            "with 'original-manifest' enabled, the contract binary may not be of optimal size."
+                .bold()
         );
     }
-    match compose_dest_path {
-        Ok(_) => Ok(crate_metadata.target_directory),
-        Err(err) => Err(err),
+
+    let cache = CacheManifest::load(&crate_metadata.target_directory);
+    let shared_inputs = Arc::new(SharedInputs::collect(
+        &crate_metadata,
+        &composable_schedule,
+    )?);
+
+    // The output of cargo is captured into the log of each component, so concurrent builds do not
+    // interleave their output.
+    let options = Arc::new(BuildOptions {
+        verbosity,
+        unstable_flags,
+        build_artifact,
+    });
+
+    let total = order.len();
+    let queue = Arc::new(BuildQueue::new(&composable_schedule, &overrides, order));
+    let aborted = Arc::new(AtomicBool::new(false));
+    let crate_metadata = Arc::new(crate_metadata);
+    let contracts_scanner = Arc::new(contracts_scanner);
+    let cache = Arc::new(Mutex::new(cache));
+    let (sender, receiver) = mpsc::channel();
+
+    let workers = (0..jobs.min(total))
+        .map(|_| {
+            let queue = queue.clone();
+            let aborted = aborted.clone();
+            let sender = sender.clone();
+            let crate_metadata = crate_metadata.clone();
+            let contracts_scanner = contracts_scanner.clone();
+            let shared_inputs = shared_inputs.clone();
+            let cache = cache.clone();
+            let options = options.clone();
+            thread::spawn(move || {
+                while let Some(job) = queue.next() {
+                    let mut log = String::new();
+                    let mut fingerprint = String::new();
+                    let result = if job.blocked || aborted.load(Ordering::SeqCst) {
+                        ComponentBuildResult::skipped(job.compose.clone())
+                    } else {
+                        fingerprint = shared_inputs.fingerprint(
+                            &job.compose,
+                            &job.settings.fingerprint_args(&job.compose),
+                            build::WASM_TARGET_RUSTFLAGS,
+                            &job.dependencies,
+                        );
+                        let cached = if force {
+                            None
+                        } else {
+                            let cache = cache.lock().expect("cache lock poisoned");
+                            cache.get(&job.compose, &fingerprint).cloned()
+                        };
+                        let result = execute_with_metadata_composable(
+                            &crate_metadata,
+                            &contracts_scanner,
+                            &job,
+                            cached.as_ref(),
+                            &options,
+                            &mut log,
+                        );
+                        if result.is_err() && !keep_going {
+                            aborted.store(true, Ordering::SeqCst);
+                        }
+                        ComponentBuildResult::new(job.compose.clone(), result)
+                    };
+                    queue.finish(&job.compose, result.code_hash.clone());
+                    let outcome = ComponentOutcome {
+                        index: job.index,
+                        fingerprint,
+                        log,
+                        result,
+                    };
+                    if sender.send(outcome).is_err() {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(sender);
+
+    // Print the buffered output of each component as soon as all of its predecessors in the
+    // schedule are done, so the output is always in schedule order.
+    let mut outcomes: Vec<Option<ComponentOutcome>> = (0..total).map(|_| None).collect();
+    let mut next_to_print = 0;
+    for outcome in receiver {
+        let index = outcome.index;
+        outcomes[index] = Some(outcome);
+        while let Some(Some(outcome)) = outcomes.get(next_to_print) {
+            if verbosity.is_verbose() {
+                print!("{}", outcome.log);
+            }
+            if let Some(err) = &outcome.result.error {
+                maybe_println!(
+                    verbosity,
+                    "{} {} {}",
+                    outcome.result.compose.as_str().bright_red().bold(),
+                    "failed:".bright_red().bold(),
+                    err
+                );
+            }
+            next_to_print += 1;
+        }
+    }
+    for worker in workers {
+        worker
+            .join()
+            .map_err(|_| anyhow::anyhow!("Composable build worker panicked"))?;
     }
+
+    let mut cache = Arc::try_unwrap(cache)
+        .map_err(|_| anyhow::anyhow!("Composable build cache still in use"))?
+        .into_inner()
+        .expect("cache lock poisoned");
+
+    let components = outcomes
+        .into_iter()
+        .map(|outcome| {
+            let outcome = outcome.expect("every scheduled component reports an outcome; qed");
+            let result = outcome.result;
+            match result.status {
+                ComponentBuildStatus::Success => cache.insert(
+                    result.compose.clone(),
+                    CacheEntry {
+                        fingerprint: outcome.fingerprint,
+                        original_size: result.original_size,
+                        optimized_size: result.optimized_size,
+                    },
+                ),
+                ComponentBuildStatus::Failed => cache.remove(&result.compose),
+                ComponentBuildStatus::Cached | ComponentBuildStatus::Skipped => (),
+            }
+            result
+        })
+        .collect::<Vec<_>>();
+    cache.save()?;
+
+    let dest_manifest = write_composable_manifest(
+        &crate_metadata.target_directory,
+        &composable_schedule.names(),
+        &components,
+    )?;
+
+    Ok(ComposableBuildResult {
+        target_directory: crate_metadata.target_directory.clone(),
+        dest_manifest,
+        components,
+    })
+}
+
+/// Resolves what `composable-build` would do for the supplied arguments, without building anything.
+///
+/// The plan lists the components in execution order, how each of them is built and where its
+/// artifacts are written to.
+pub(crate) fn plan(args: &ExecuteArgs) -> Result<ComposableBuildPlan> {
+    let crate_metadata = CrateMetadata::collect(&args.manifest_path)?;
+    let composable_schedule = crate_metadata.composable_schedule()?;
+    let contracts_scanner = WatContractsScanner::discover(&crate_metadata, &composable_schedule)?;
+
+    let components = composable_schedule
+        .execution_order_of(&args.compose)?
+        .into_iter()
+        .map(|compose| {
+            let target_directory =
+                get_compose_target_dest(compose.clone(), crate_metadata.target_directory.clone());
+            let wat_contract = contracts_scanner.find_by_name(&compose);
+            let (kind, cargo_args) = match wat_contract {
+                Some(_) => (ComponentKind::Wat, Vec::new()),
+                None => {
+                    let settings =
+                        component_settings(&composable_schedule, &args.overrides, &compose);
+                    let cargo_args = build::cargo_args_for_wasm_target(
+                        &target_directory,
+                        &settings.features(&compose),
+                        settings.build_mode,
+                    );
+                    (ComponentKind::Cargo, cargo_args)
+                }
+            };
+            let dest_bundle = match (kind, args.build_artifact) {
+                (ComponentKind::Cargo, BuildArtifacts::All) => {
+                    Some(get_dest_bundle_path(compose.clone(), &crate_metadata))
+                }
+                _ => None,
+            };
+            ComponentBuildPlan {
+                dependencies: composable_schedule.dependencies_of(&compose).to_vec(),
+                source: wat_contract.map(ToString::to_string),
+                dest_wasm: get_dest_wasm_path(compose.clone(), &crate_metadata),
+                compose,
+                kind,
+                cargo_args,
+                target_directory,
+                dest_bundle,
+            }
+        })
+        .collect();
+    Ok(ComposableBuildPlan {
+        rustflags: build::WASM_TARGET_RUSTFLAGS.to_string(),
+        components,
+    })
 }
+
 /// Executes build of the smart-contract which produces a wasm binary that is ready for deploying.
 ///
 /// It does so by invoking `cargo build` and then post processing the final binary.
 ///
+/// All output is written to `log` instead of stdout, since components might be built
+/// concurrently.
+///
+/// If a `cached` entry is supplied, i.e. the build inputs of the component did not change, and
+/// the component's Wasm file still exists, the build is skipped.
+///
 /// # Note
 ///
-/// Uses the supplied `CrateMetadata`. If an instance is not available use [`execute_build`]
-pub(crate) fn execute_with_metadata_composable(
+/// Uses the supplied `CrateMetadata`, which [`execute`] collects once for all components.
+fn execute_with_metadata_composable(
     crate_metadata: &CrateMetadata,
-    wat_contracts_scanner: &mut WatContractsScanner,
-    compose: String,
-    verbosity: Option<Verbosity>,
-    unstable_options: UnstableFlags,
-) -> Result<PathBuf> {
-    match wat_contracts_scanner.find_by_name(compose.clone()) {
+    wat_contracts_scanner: &WatContractsScanner,
+    job: &BuildJob,
+    cached: Option<&CacheEntry>,
+    options: &BuildOptions,
+    log: &mut String,
+) -> Result<ComponentArtifacts> {
+    let compose = job.compose.clone();
+    let settings = &job.settings;
+    let dest_wasm = get_dest_wasm_path(compose.clone(), crate_metadata);
+    let wat_contract = wat_contracts_scanner.find_by_name(&compose);
+    // Metadata can only be generated for ink! components, not for WAT contracts.
+    let metadata_result = match (wat_contract, options.build_artifact) {
+        (None, BuildArtifacts::All) => Some(MetadataResult {
+            dest_metadata: get_dest_metadata_path(compose.clone(), crate_metadata),
+            dest_bundle: get_dest_bundle_path(compose.clone(), crate_metadata),
+        }),
+        _ => None,
+    };
+    let steps = match metadata_result {
+        Some(_) => BuildArtifacts::All.steps(),
+        None => BuildArtifacts::CodeOnly.steps(),
+    };
+
+    let up_to_date = match &metadata_result {
+        Some(metadata) => dest_wasm.exists() && metadata.dest_bundle.exists(),
+        None => dest_wasm.exists(),
+    };
+    if let Some(cached) = cached.filter(|_| up_to_date) {
+        writeln!(
+            log,
+            "{} {}",
+            "Skipping build; inputs unchanged - component:"
+                .bright_green()
+                .bold(),
+            compose.as_str().bright_green().bold()
+        )?;
+        let optimization = cached.original_size.zip(cached.optimized_size).map(
+            |(original_size, optimized_size)| OptimizationResult {
+                dest_wasm: dest_wasm.clone(),
+                original_size,
+                optimized_size,
+            },
+        );
+        return Ok(ComponentArtifacts {
+            code_hash: code_hash(&dest_wasm)?,
+            dest_wasm,
+            optimization,
+            metadata_result,
+            cached: true,
+        });
+    }
+
+    match wat_contract {
         Some(wat_contract) => {
-            println!(
-                "{} {} {}",
-                "[1/3]".bold(),
+            writeln!(
+                log,
+                "{} {} {} ({})",
+                format!("[1/{}]", steps).bold(),
                 "Skipping cargo build; found WAT contract - component:"
                     .bright_green()
                     .bold(),
-                compose.as_str().bright_green().bold()
-            );
-            println!(
+                compose.as_str().bright_green().bold(),
+                wat_contract
+            )?;
+            writeln!(
+                log,
                 "{} {} {}",
-                "[2/3]".bold(),
+                format!("[2/{}]", steps).bold(),
                 "Compiling scanned WAT contract to WASM - component:"
                     .bright_green()
                     .bold(),
                 compose.as_str().bright_green().bold()
-            );
-            compile_wat_to_wasm(compose.clone(), &crate_metadata, wat_contract)?;
+            )?;
+            compile_wat_to_wasm(compose.clone(), crate_metadata, wat_contract)?;
         }
         None => {
-            println!(
+            writeln!(
+                log,
                 "{} {} {}",
-                "[1/3]".bold(),
+                format!("[1/{}]", steps).bold(),
                 "Building cargo project - component:".bright_green().bold(),
                 compose.as_str().bright_green().bold()
-            );
-            build_cargo_project_compose(
-                &crate_metadata,
-                compose.clone(),
-                verbosity,
-                unstable_options,
             )?;
-            println!(
+            let output = build_cargo_project_compose(
+                crate_metadata,
+                job,
+                options.verbosity,
+                &options.unstable_flags,
+            )?;
+            log.push_str(&output);
+            writeln!(
+                log,
                 " {} {} {}",
-                "[2/3]".bold(),
+                format!("[2/{}]", steps).bold(),
                 "Post processing wasm file - component:"
                     .bright_green()
                     .bold(),
                 compose.as_str().bright_green().bold()
-            );
-            post_process_wasm_compose(&crate_metadata, compose.clone())?;
+            )?;
+            build::post_process_wasm_file(
+                &get_original_wasm_path(compose.clone(), crate_metadata),
+                &dest_wasm,
+                settings.max_memory_pages,
+            )?;
         }
     };
 
-    println!(
+    writeln!(
+        log,
         " {} {} {}",
-        "[3/3]".bold(),
+        format!("[3/{}]", steps).bold(),
         "Optimizing wasm file - component:".bright_green().bold(),
         compose.as_str().bright_green().bold(),
-    );
-    optimize_wasm_compose(&crate_metadata, compose.clone())?;
-    Ok(crate_metadata.dest_wasm.clone())
+    )?;
+    let optimization = build::optimize_wasm_file(
+        &dest_wasm,
+        &compose,
+        settings.optimization_passes,
+        settings.keep_debug_symbols,
+    )?;
+    writeln!(
+        log,
+        " Original wasm size: {:.1}K, Optimized: {:.1}K",
+        optimization.original_size, optimization.optimized_size
+    )?;
+
+    let metadata_result = match metadata_result {
+        Some(_) => {
+            writeln!(
+                log,
+                " {} {} {}",
+                format!("[4/{}]", steps).bold(),
+                "Generating metadata and bundle - component:"
+                    .bright_green()
+                    .bold(),
+                compose.as_str().bright_green().bold(),
+            )?;
+            let artifacts = metadata::MetadataArtifacts {
+                target_directory: get_compose_target_dest(
+                    compose.clone(),
+                    crate_metadata.target_directory.clone(),
+                ),
+                artifact_name: compose.clone(),
+                features: settings.features(&compose),
+                settings: metadata::BuildSettings {
+                    build_mode: settings.build_mode,
+                    optimization_passes: settings.optimization_passes,
+                    keep_debug_symbols: settings.keep_debug_symbols,
+                    rustflags: build::WASM_TARGET_RUSTFLAGS.to_string(),
+                },
+                env: cargo_env(&job.dependencies),
+            };
+            Some(metadata::generate(
+                crate_metadata,
+                &dest_wasm,
+                &artifacts,
+                options.verbosity,
+                None,
+                &options.unstable_flags,
+                Some(log),
+            )?)
+        }
+        None => None,
+    };
+
+    Ok(ComponentArtifacts {
+        code_hash: code_hash(&dest_wasm)?,
+        dest_wasm,
+        optimization: Some(optimization),
+        metadata_result,
+        cached: false,
+    })
+}
+
+/// Returns the hex encoded hash of the Wasm code at `path`, as it is stored on chain.
+fn code_hash(path: &Path) -> Result<String> {
+    let code = fs::read(path).context(format!("Reading Wasm file {}", path.display()))?;
+    Ok(impl_serde::serialize::to_hex(
+        &metadata::blake2_hash(&code).0,
+        false,
+    ))
+}
+
+/// Returns the name of the environment variable holding the code hash of `compose`.
+///
+/// The variable is set for the cargo invocations of components which depend on `compose`, so
+/// they can embed its code hash at compile time, e.g. via `env!("FLIPPER_CODE_HASH")`.
+pub fn code_hash_env_var(compose: &str) -> String {
+    format!("{}_CODE_HASH", compose.to_uppercase().replace('-', "_"))
+}
+
+/// Returns the environment variables holding the code hashes of the supplied dependencies.
+fn cargo_env(dependencies: &[(String, String)]) -> Vec<(String, String)> {
+    dependencies
+        .iter()
+        .map(|(compose, code_hash)| (code_hash_env_var(compose), code_hash.clone()))
+        .collect()
 }
 
-#[cfg(feature = "test-ci-only")]
 #[cfg(test)]
 mod tests {
-    use crate::{cmd, util::tests::with_tmp_dir, workspace::ManifestPath, UnstableFlags};
+    use super::{
+        cargo_env, component_settings, write_composable_manifest, BuildQueue, ComponentArtifacts,
+        ComponentBuildResult, ComponentBuildStatus, ComposableBuildResult,
+    };
+    use crate::{
+        cmd::{build, metadata::MetadataResult},
+        crate_metadata::{ComponentBuildConfig, ComposableScheduleMetadata},
+        util::tests::with_tmp_dir,
+        BuildMode, OptimizationPasses,
+    };
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    fn built_in(target: &Path, compose: &str) -> ComponentBuildResult {
+        let component_dir = target.join(compose);
+        let artifacts = ComponentArtifacts {
+            dest_wasm: component_dir.join(format!("{}.wasm", compose)),
+            code_hash: "0x00".to_string(),
+            optimization: None,
+            metadata_result: Some(MetadataResult {
+                dest_metadata: component_dir.join("metadata.json"),
+                dest_bundle: component_dir.join(format!("{}.contract", compose)),
+            }),
+            cached: false,
+        };
+        ComponentBuildResult::new(compose.to_string(), Ok(artifacts))
+    }
+
+    fn built(compose: &str) -> ComponentBuildResult {
+        built_in(Path::new("/path/to/target"), compose)
+    }
+
+    #[test]
+    fn failed_component_must_not_be_swallowed_by_later_success() {
+        // given
+        let failed = ComponentBuildResult::new(
+            "first".to_string(),
+            Err(anyhow::anyhow!("compilation failed")),
+        );
+        let result = ComposableBuildResult {
+            target_directory: PathBuf::from("/path/to/target"),
+            dest_manifest: PathBuf::from("/path/to/target/composable-manifest.json"),
+            components: vec![failed, built("second")],
+        };
+
+        // then
+        assert!(!result.is_success());
+        assert_eq!(result.failed(), 1);
+        assert_eq!(result.components[0].status, ComponentBuildStatus::Failed);
+        assert_eq!(
+            result.components[0].error.as_deref(),
+            Some("compilation failed")
+        );
+        assert_eq!(result.components[1].status, ComponentBuildStatus::Success);
+    }
+
+    #[test]
+    fn skipped_components_are_not_successful() {
+        // given
+        let result = ComposableBuildResult {
+            target_directory: PathBuf::from("/path/to/target"),
+            dest_manifest: PathBuf::from("/path/to/target/composable-manifest.json"),
+            components: vec![
+                built("first"),
+                ComponentBuildResult::skipped("second".to_string()),
+            ],
+        };
+
+        // then
+        assert!(!result.is_success());
+        assert_eq!(result.failed(), 0);
+    }
+
+    #[test]
+    fn composable_manifest_lists_built_components() {
+        with_tmp_dir(|path| {
+            // given
+            let names = ["token", "dex", "oracle"];
+            write_composable_manifest(
+                path,
+                &names,
+                &[built_in(path, "dex"), built_in(path, "oracle")],
+            )?;
+            let components = vec![
+                built_in(path, "token"),
+                ComponentBuildResult::new("dex".to_string(), Err(anyhow::anyhow!("failed"))),
+            ];
+
+            // when
+            let dest_manifest = write_composable_manifest(path, &names, &components)?;
+
+            // then
+            let manifest: serde_json::Value = serde_json::from_slice(&fs::read(dest_manifest)?)?;
+            let entry = |name: &str| {
+                serde_json::json!({
+                    "name": name,
+                    "code_hash": "0x00",
+                    "wasm": format!("{0}/{0}.wasm", name),
+                    "metadata": format!("{}/metadata.json", name),
+                    "bundle": format!("{0}/{0}.contract", name),
+                })
+            };
+            assert_eq!(
+                manifest,
+                serde_json::json!({ "components": [entry("token"), entry("oracle")] })
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn command_line_settings_take_precedence_over_schedule() {
+        // given
+        let schedule: ComposableScheduleMetadata = serde_json::from_value(serde_json::json!({
+            "composables": [
+                "flipper",
+                { "name": "dex", "features": ["oracle"], "build-mode": "debug", "optimization-passes": "z" },
+                { "name": "oracle", "build-mode": "debug" }
+            ]
+        }))
+        .unwrap();
+        let overrides = ComponentBuildConfig {
+            build_mode: Some(BuildMode::Release),
+            ..Default::default()
+        };
+
+        // when
+        let flipper = component_settings(&schedule, &Default::default(), "flipper");
+        let dex = component_settings(&schedule, &overrides, "dex");
+        let oracle = component_settings(&schedule, &Default::default(), "oracle");
+
+        // then
+        assert_eq!(flipper.features("flipper"), vec!["flipper"]);
+        assert_eq!(flipper.build_mode, BuildMode::Release);
+        assert_eq!(flipper.optimization_passes, OptimizationPasses::Three);
+        assert_eq!(dex.features("dex"), vec!["dex", "oracle"]);
+        assert_eq!(dex.build_mode, BuildMode::Release);
+        assert_eq!(dex.optimization_passes, OptimizationPasses::Z);
+        assert_eq!(oracle.build_mode, BuildMode::Debug);
+        assert_ne!(flipper.fingerprint_args("dex"), dex.fingerprint_args("dex"));
+    }
+
+    #[test]
+    fn planned_cargo_arguments_follow_the_build_mode() {
+        let target_dir = Path::new("/path/to/target/dex");
+        let features = vec!["dex".to_string()];
+
+        let debug = build::cargo_args_for_wasm_target(target_dir, &features, BuildMode::Debug);
+        let release = build::cargo_args_for_wasm_target(target_dir, &features, BuildMode::Release);
+
+        assert!(debug.contains(&"--target-dir=/path/to/target/dex".to_string()));
+        assert!(debug.contains(&"--features=dex,ink_env/ink-debug".to_string()));
+        assert!(release.contains(&"--features=dex".to_string()));
+        assert!(release.contains(&"-Zbuild-std-features=panic_immediate_abort".to_string()));
+    }
+
+    #[test]
+    fn queue_hands_out_components_after_their_dependencies() {
+        // given
+        let schedule = ComposableScheduleMetadata {
+            composables: vec!["dex".into(), "token".into(), "oracle".into()],
+            contract: None,
+            schedule: Some(
+                vec![
+                    ("dex".to_string(), vec!["token".to_string()]),
+                    ("oracle".to_string(), vec!["dex".to_string()]),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            deploy: None,
+            exec: None,
+            sources: None,
+        };
+        let queue = BuildQueue::new(
+            &schedule,
+            &Default::default(),
+            schedule.execution_order().unwrap(),
+        );
+
+        // when
+        let token = queue.next().unwrap();
+        queue.finish(&token.compose, Some("0x01".to_string()));
+        let dex = queue.next().unwrap();
+        queue.finish(&dex.compose, None);
+        let oracle = queue.next().unwrap();
+
+        // then
+        assert_eq!(token.compose, "token");
+        assert_eq!(dex.compose, "dex");
+        assert_eq!(dex.dependencies, vec![("token".into(), "0x01".into())]);
+        assert_eq!(
+            cargo_env(&dex.dependencies),
+            vec![("TOKEN_CODE_HASH".into(), "0x01".into())]
+        );
+        assert!(std::env::var_os("TOKEN_CODE_HASH").is_none());
+        assert!(!dex.blocked);
+        assert_eq!(oracle.compose, "oracle");
+        assert!(oracle.blocked);
+        assert!(queue.next().is_none());
+    }
+}
+
+#[cfg(feature = "test-ci-only")]
+#[cfg(test)]
+mod tests_ci_only {
+    use super::ExecuteArgs;
+    use crate::{
+        cmd, util::tests::with_tmp_dir, workspace::ManifestPath, BuildArtifacts, UnstableFlags,
+        Verbosity,
+    };
 
     #[test]
     fn build_template() {
@@ -542,7 +1475,19 @@ mod tests {
             cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
             let manifest_path =
                 ManifestPath::new(&path.join("new_project").join("Cargo.toml")).unwrap();
-            super::execute(&manifest_path, None, UnstableFlags::default()).expect("build failed");
+            let args = ExecuteArgs {
+                manifest_path,
+                verbosity: Verbosity::Default,
+                build_artifact: BuildArtifacts::All,
+                unstable_flags: UnstableFlags::default(),
+                overrides: Default::default(),
+                jobs: 1,
+                keep_going: false,
+                force: false,
+                compose: Vec::new(),
+            };
+            let res = super::execute(args).expect("build failed");
+            assert!(res.is_success());
             Ok(())
         })
     }
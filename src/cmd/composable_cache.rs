@@ -0,0 +1,282 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Content-hash based caching of composable component builds.
+//!
+//! For every component a fingerprint of all build inputs is recorded in a cache manifest in the
+//! target directory. A component whose fingerprint did not change since the last successful
+//! build, and whose Wasm file still exists, does not need to be built again.
+
+use crate::{
+    crate_metadata::{ComposableScheduleMetadata, CrateMetadata},
+    util,
+};
+use anyhow::{Context, Result};
+use blake2::digest::{Update as _, VariableOutput as _};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const CACHE_FILE: &str = "composable-build-cache.json";
+
+/// Version of the cache manifest format, bumped whenever the fingerprint computation changes.
+const CACHE_VERSION: u32 = 2;
+
+/// File extensions of sources which are taken into account for the fingerprint.
+const SOURCE_EXTENSIONS: [&str; 4] = ["rs", "toml", "wat", "wasm"];
+
+/// Cached result of a previous component build.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CacheEntry {
+    /// Fingerprint of all build inputs of the component.
+    pub fingerprint: String,
+    /// The Wasm size before optimizations, if `wasm-opt` was run.
+    pub original_size: Option<f64>,
+    /// The Wasm size after optimizations, if `wasm-opt` was run.
+    pub optimized_size: Option<f64>,
+}
+
+/// Manifest of cached component builds, stored in the target directory.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CacheManifest {
+    version: u32,
+    components: BTreeMap<String, CacheEntry>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl CacheManifest {
+    /// Loads the cache manifest from the supplied target directory.
+    ///
+    /// A missing, unreadable or outdated manifest results in an empty cache.
+    pub fn load(target_directory: &Path) -> Self {
+        let path = target_directory.join(CACHE_FILE);
+        let manifest = fs::read(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<CacheManifest>(&contents).ok())
+            .filter(|manifest| manifest.version == CACHE_VERSION);
+        match manifest {
+            Some(manifest) => CacheManifest { path, ..manifest },
+            None => {
+                log::debug!("No usable build cache found at '{}'", path.display());
+                CacheManifest {
+                    version: CACHE_VERSION,
+                    components: BTreeMap::new(),
+                    path,
+                }
+            }
+        }
+    }
+
+    /// Returns the cached entry of `compose` if its fingerprint matches.
+    pub fn get(&self, compose: &str, fingerprint: &str) -> Option<&CacheEntry> {
+        self.components
+            .get(compose)
+            .filter(|entry| entry.fingerprint == fingerprint)
+    }
+
+    /// Records the entry of a successfully built component.
+    pub fn insert(&mut self, compose: String, entry: CacheEntry) {
+        self.components.insert(compose, entry);
+    }
+
+    /// Forgets about a component, e.g. because its build failed.
+    pub fn remove(&mut self, compose: &str) {
+        self.components.remove(compose);
+    }
+
+    /// Writes the cache manifest back to the target directory.
+    pub fn save(&self) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&self.path, contents).context(format!(
+            "Writing composable build cache to '{}'",
+            self.path.display()
+        ))
+    }
+}
+
+/// Build inputs shared by all components of a composable schedule.
+pub(crate) struct SharedInputs {
+    sources: String,
+    cargo_lock: String,
+    wasm_opt_version: String,
+}
+
+impl SharedInputs {
+    /// Hashes the contract sources and `Cargo.lock`, and determines the `wasm-opt` version.
+    pub fn collect(
+        crate_metadata: &CrateMetadata,
+        schedule: &ComposableScheduleMetadata,
+    ) -> Result<Self> {
+        let source_dir = crate_metadata.manifest_path.absolute_directory()?;
+        let target_dir = &crate_metadata.target_directory;
+
+        let mut files = util::collect_source_files(&source_dir, target_dir, &SOURCE_EXTENSIONS);
+        // Component files referenced from the manifest may live outside of the crate directory.
+        files.extend(
+            schedule
+                .sources
+                .iter()
+                .flat_map(|sources| sources.values())
+                .map(|path| source_dir.join(path)),
+        );
+
+        let mut sources = Vec::new();
+        for file in files {
+            let relative = file.strip_prefix(&source_dir).unwrap_or(&file);
+            sources.extend_from_slice(relative.to_string_lossy().as_bytes());
+            sources.extend_from_slice(
+                &fs::read(&file).context(format!("Reading source file {}", file.display()))?,
+            );
+        }
+
+        let cargo_lock = crate_metadata.cargo_meta.workspace_root.join("Cargo.lock");
+        let cargo_lock = fs::read(&cargo_lock).unwrap_or_default();
+
+        Ok(SharedInputs {
+            sources: hash_hex(&sources),
+            cargo_lock: hash_hex(&cargo_lock),
+            wasm_opt_version: util::wasm_opt_version().unwrap_or_else(|| "none".to_string()),
+        })
+    }
+
+    /// Computes the fingerprint of a single component.
+    ///
+    /// `cargo_args` are the component specific arguments passed to cargo, e.g. its features.
+    /// `dependencies` are the names and code hashes of the components it depends on.
+    pub fn fingerprint(
+        &self,
+        compose: &str,
+        cargo_args: &[String],
+        rustflags: &str,
+        dependencies: &[(String, String)],
+    ) -> String {
+        let dependencies = dependencies
+            .iter()
+            .map(|(name, code_hash)| format!("{}:{}", name, code_hash))
+            .collect::<Vec<_>>();
+        let inputs = [
+            format!("version={}", CACHE_VERSION),
+            format!("compose={}", compose),
+            format!("cargo-args={}", cargo_args.join(" ")),
+            format!("rustflags={}", rustflags),
+            format!("dependencies={}", dependencies.join(" ")),
+            format!("sources={}", self.sources),
+            format!("cargo-lock={}", self.cargo_lock),
+            format!("wasm-opt={}", self.wasm_opt_version),
+        ];
+        hash_hex(inputs.join("\n").as_bytes())
+    }
+}
+
+/// Returns the hex encoded blake2 hash of the supplied bytes.
+fn hash_hex(bytes: &[u8]) -> String {
+    let mut blake2 = blake2::VarBlake2b::new_keyed(&[], 32);
+    blake2.update(bytes);
+    let mut hex = String::with_capacity(64);
+    blake2.finalize_variable(|result| {
+        for byte in result {
+            hex.push_str(&format!("{:02x}", byte));
+        }
+    });
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheEntry, CacheManifest, SharedInputs};
+    use crate::util::tests::with_tmp_dir;
+
+    fn shared_inputs() -> SharedInputs {
+        SharedInputs {
+            sources: "sources".into(),
+            cargo_lock: "lock".into(),
+            wasm_opt_version: "wasm-opt version 101".into(),
+        }
+    }
+
+    fn entry(fingerprint: &str) -> CacheEntry {
+        CacheEntry {
+            fingerprint: fingerprint.into(),
+            original_size: Some(2.0),
+            optimized_size: Some(1.0),
+        }
+    }
+
+    #[test]
+    fn fingerprint_changes_with_component_inputs() {
+        let inputs = shared_inputs();
+        let args = vec!["--features=flipper".to_string()];
+        let deps = vec![("token".to_string(), "0x01".to_string())];
+        let fingerprint = inputs.fingerprint("flipper", &args, "-C opt", &deps);
+
+        assert_eq!(
+            fingerprint,
+            inputs.fingerprint("flipper", &args, "-C opt", &deps)
+        );
+        assert_ne!(
+            fingerprint,
+            inputs.fingerprint("other", &args, "-C opt", &deps)
+        );
+        assert_ne!(
+            fingerprint,
+            inputs.fingerprint("flipper", &[], "-C opt", &deps)
+        );
+        assert_ne!(
+            fingerprint,
+            inputs.fingerprint("flipper", &args, "-C other", &deps)
+        );
+        let changed_deps = vec![("token".to_string(), "0x02".to_string())];
+        assert_ne!(
+            fingerprint,
+            inputs.fingerprint("flipper", &args, "-C opt", &changed_deps)
+        );
+
+        let changed_sources = SharedInputs {
+            sources: "changed".into(),
+            ..shared_inputs()
+        };
+        assert_ne!(
+            fingerprint,
+            changed_sources.fingerprint("flipper", &args, "-C opt", &deps)
+        );
+    }
+
+    #[test]
+    fn cache_manifest_round_trips() {
+        with_tmp_dir(|path| {
+            // given
+            let mut manifest = CacheManifest::load(path);
+            assert!(manifest.get("flipper", "abc").is_none());
+            manifest.insert("flipper".into(), entry("abc"));
+            manifest.insert("incrementer".into(), entry("def"));
+            manifest.remove("incrementer");
+
+            // when
+            manifest.save()?;
+            let loaded = CacheManifest::load(path);
+
+            // then
+            assert_eq!(loaded.get("flipper", "abc"), Some(&entry("abc")));
+            assert!(loaded.get("flipper", "changed").is_none());
+            assert!(loaded.get("incrementer", "def").is_none());
+            Ok(())
+        })
+    }
+}
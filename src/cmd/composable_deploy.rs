@@ -0,0 +1,303 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{composable_build::get_dest_wasm_path, metadata::blake2_hash};
+use crate::{
+    crate_metadata::{self, ComposableDeployConfig, CrateMetadata},
+    workspace::ManifestPath,
+    ExtrinsicOpts,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use sp_core::H256;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Backend which deploys the code of a component to the VM named in its deploy entry.
+pub trait Deployer {
+    /// Name of the VM, as listed in [`crate_metadata::COMPOSABLE_VMS`].
+    fn name(&self) -> &'static str;
+
+    /// Deploys the Wasm code at `wasm_path`, returning the hash of the deployed code.
+    fn deploy(&self, extrinsic_opts: &ExtrinsicOpts, wasm_path: &Path) -> Result<H256>;
+}
+
+/// Uploads the code to `pallet-contracts` via `put_code`.
+pub struct PalletContractsDeployer;
+
+impl Deployer for PalletContractsDeployer {
+    fn name(&self) -> &'static str {
+        "pallet-contracts"
+    }
+
+    fn deploy(&self, extrinsic_opts: &ExtrinsicOpts, wasm_path: &Path) -> Result<H256> {
+        super::execute_deploy(extrinsic_opts, Some(&wasm_path.to_path_buf()))
+    }
+}
+
+/// Prepares the code for the VM hosted by the runtime gateway.
+///
+/// The runtime gateway receives the code of a component along with every execution, see
+/// `composable-exec`, so nothing is uploaded. The code is only checked to be valid Wasm.
+pub struct RuntimeGatewayDeployer;
+
+impl Deployer for RuntimeGatewayDeployer {
+    fn name(&self) -> &'static str {
+        "runtime-gateway"
+    }
+
+    fn deploy(&self, _extrinsic_opts: &ExtrinsicOpts, wasm_path: &Path) -> Result<H256> {
+        let code =
+            fs::read(wasm_path).context(format!("Failed to open {}", wasm_path.display()))?;
+        parity_wasm::deserialize_buffer::<parity_wasm::elements::Module>(&code).context(
+            format!("{} is not a valid Wasm module", wasm_path.display()),
+        )?;
+        Ok(H256(blake2_hash(&code).0))
+    }
+}
+
+/// Returns all available deployment backends.
+fn deployers() -> Vec<Box<dyn Deployer>> {
+    vec![
+        Box::new(PalletContractsDeployer),
+        Box::new(RuntimeGatewayDeployer),
+    ]
+}
+
+/// Returns the deployment backend for the supplied VM name or one of its aliases.
+pub fn deployer_for(vm: &str) -> Result<Box<dyn Deployer>> {
+    let name = crate_metadata::resolve_vm(vm)?;
+    let deployer = deployers()
+        .into_iter()
+        .find(|deployer| deployer.name() == name)
+        .expect("every VM of COMPOSABLE_VMS has a deployer; qed");
+    Ok(deployer)
+}
+
+/// Result of deploying a single component.
+pub struct ComponentDeployResult {
+    /// Name of the deployed component.
+    pub compose: String,
+    /// Name of the backend the component was deployed with.
+    pub vm: &'static str,
+    /// Hash of the deployed code.
+    pub code_hash: H256,
+}
+
+/// Resolves the entries of the `deploy` section of the composable schedule, together with their
+/// deployment backends, in the order in which they are deployed.
+///
+/// If components are selected via `compose`, only the entries of those components and of their
+/// transitive dependencies are returned, like `composable-build --compose` builds them.
+/// Dependencies are deployed before the components depending on them.
+fn resolve_deployments(
+    crate_metadata: &CrateMetadata,
+    compose: &[String],
+) -> Result<Vec<(ComposableDeployConfig, Box<dyn Deployer>)>> {
+    let composable_schedule = crate_metadata.composable_schedule()?;
+    // Deploy dependencies before the components depending on them.
+    let order = composable_schedule.execution_order_of(compose)?;
+    let mut deploy_schedule = match composable_schedule.deploy {
+        Some(deploy_schedule) => deploy_schedule,
+        None => anyhow::bail!("Nothing to deploy. Empty deploy key of composable metadata."),
+    };
+    if !compose.is_empty() {
+        deploy_schedule.retain(|deploy| order.contains(&deploy.compose));
+        if deploy_schedule.is_empty() {
+            anyhow::bail!(
+                "Nothing to deploy. None of the selected components has an entry in the deploy key of composable metadata."
+            );
+        }
+    }
+    deploy_schedule.sort_by_key(|deploy| {
+        order
+            .iter()
+            .position(|compose| *compose == deploy.compose)
+            .unwrap_or(order.len())
+    });
+
+    deploy_schedule
+        .into_iter()
+        .map(|deploy| {
+            let deployer = deployer_for(&deploy.vm)
+                .context(format!("Deploying component {}", deploy.compose))?;
+            Ok((deploy, deployer))
+        })
+        .collect()
+}
+
+/// Resolved deployment of a single entry of the `deploy` section.
+#[derive(serde::Serialize)]
+pub struct ComponentDeployPlan {
+    /// Name of the component to deploy.
+    pub compose: String,
+    /// Name of the backend the component would be deployed with.
+    pub vm: &'static str,
+    /// URL of the node the component would be deployed to.
+    pub url: url::Url,
+    /// Path to the Wasm file which would be deployed.
+    pub wasm: PathBuf,
+    /// Whether the Wasm file exists, i.e. the component has been built.
+    pub built: bool,
+}
+
+/// Resolved plan of `composable-deploy`, as printed by `composable-deploy --dry-run`.
+#[derive(serde::Serialize)]
+pub struct ComposableDeployPlan {
+    /// The entries to deploy, in deployment order.
+    pub deployments: Vec<ComponentDeployPlan>,
+}
+
+impl ComposableDeployPlan {
+    /// Returns the plan serialized as pretty-printed JSON.
+    pub fn serialize_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Renders the node URL, VM and Wasm file of every entry, in deployment order.
+    pub fn display(&self) -> String {
+        let mut out = format!(
+            "{}\n",
+            "Composable deploy plan (dry run), nothing is deployed"
+                .bright_blue()
+                .bold()
+        );
+        for (index, deployment) in self.deployments.iter().enumerate() {
+            let missing = if deployment.built {
+                "".normal()
+            } else {
+                " (not built yet)".yellow()
+            };
+            out.push_str(&format!(
+                "\n {} {}\n   vm:   {}\n   url:  {}\n   wasm: {}{}\n",
+                format!("[{}/{}]", index + 1, self.deployments.len()).bold(),
+                deployment.compose.bold(),
+                deployment.vm,
+                deployment.url,
+                deployment.wasm.display(),
+                missing
+            ));
+        }
+        out
+    }
+}
+
+/// Resolves what `composable-deploy` would do for the selected components, without deploying
+/// anything.
+pub(crate) fn plan(
+    manifest_path: &ManifestPath,
+    compose: &[String],
+) -> Result<ComposableDeployPlan> {
+    let crate_metadata = CrateMetadata::collect(manifest_path)?;
+    let deployments = resolve_deployments(&crate_metadata, compose)?
+        .into_iter()
+        .map(|(deploy, deployer)| {
+            let wasm = get_dest_wasm_path(deploy.compose.clone(), &crate_metadata);
+            ComponentDeployPlan {
+                built: wasm.exists(),
+                compose: deploy.compose,
+                vm: deployer.name(),
+                url: deploy.url,
+                wasm,
+            }
+        })
+        .collect();
+    Ok(ComposableDeployPlan { deployments })
+}
+
+/// Deploys the entries of the `deploy` section of the composable schedule.
+///
+/// If components are selected via `compose`, only their entries are deployed. Dependencies are
+/// deployed before the components depending on them. The VMs of all entries
+/// are resolved before anything is deployed, so an unknown VM does not leave the schedule
+/// partially deployed.
+pub(crate) fn execute(
+    manifest_path: &ManifestPath,
+    suri: &str,
+    password: Option<&str>,
+    compose: &[String],
+) -> Result<Vec<ComponentDeployResult>> {
+    let crate_metadata = CrateMetadata::collect(manifest_path)?;
+    let deployments = resolve_deployments(&crate_metadata, compose)?;
+
+    let mut results = Vec::with_capacity(deployments.len());
+    for (deploy, deployer) in deployments {
+        println!("Deploying: {:?}", deploy);
+        let component_extrinsic_opts = ExtrinsicOpts {
+            url: deploy.url.clone(),
+            suri: suri.to_string(),
+            password: password.map(ToString::to_string),
+        };
+        let dest_wasm_path = get_dest_wasm_path(deploy.compose.clone(), &crate_metadata);
+        let code_hash = deployer
+            .deploy(&component_extrinsic_opts, &dest_wasm_path)
+            .context(format!(
+                "Deploying component {} to {}",
+                deploy.compose,
+                deployer.name()
+            ))?;
+        println!(
+            "{} - {} {:?}",
+            deploy.compose.bright_blue().bold(),
+            "successfully deployed byte code with hash: ".bright_blue(),
+            code_hash
+        );
+        results.push(ComponentDeployResult {
+            compose: deploy.compose,
+            vm: deployer.name(),
+            code_hash,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deployer_for, deployers};
+    use crate::crate_metadata::COMPOSABLE_VMS;
+
+    #[test]
+    fn deployers_are_found_by_name_and_alias() {
+        assert_eq!(
+            deployer_for("pallet-contracts").unwrap().name(),
+            "pallet-contracts"
+        );
+        assert_eq!(deployer_for("wasm").unwrap().name(), "pallet-contracts");
+        assert_eq!(
+            deployer_for("runtime-gateway").unwrap().name(),
+            "runtime-gateway"
+        );
+    }
+
+    #[test]
+    fn every_vm_has_a_deployer() {
+        let names = deployers().iter().map(|d| d.name()).collect::<Vec<_>>();
+        let vms = COMPOSABLE_VMS.iter().map(|(vm, _)| *vm).collect::<Vec<_>>();
+        assert_eq!(names, vms);
+    }
+
+    #[test]
+    fn unknown_vm_is_rejected() {
+        let err = deployer_for("evm").err().unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown VM 'evm', supported VMs are: pallet-contracts (wasm, contracts), runtime-gateway"
+        );
+    }
+}
@@ -0,0 +1,341 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{address::parse_account, composable_build::get_dest_wasm_path};
+use crate::{
+    crate_metadata::{self, ComposableExecConfig, CrateMetadata},
+    network::NetworkConfig,
+    workspace::ManifestPath,
+    ExtrinsicOpts, GasOpts, HexData,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use sp_core::{
+    crypto::{AccountId32, Pair},
+    sr25519,
+};
+use std::{
+    fmt::{Display, Formatter, Result as DisplayResult},
+    fs,
+    str::FromStr,
+};
+
+/// Gas limit of executions via the runtime gateway, if the node cannot estimate it.
+const RUNTIME_GATEWAY_GAS_LIMIT: u64 = 500_000_000;
+
+/// Gas limit of executions via the contracts gateway, if the node cannot estimate it.
+const CONTRACTS_GATEWAY_GAS_LIMIT: u64 = 3_875_000_000;
+
+/// Gateway through which a component is executed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecGateway {
+    /// Versatile execution via the runtime gateway, see `call-runtime-gateway`.
+    Runtime,
+    /// Execution via the contracts gateway, see `call-contracts-gateway`.
+    Contracts,
+}
+
+impl ExecGateway {
+    /// Returns the gas limit of executions whose gas cannot be estimated via a dry run.
+    pub(crate) fn fallback_gas_limit(&self) -> u64 {
+        match self {
+            Self::Runtime => RUNTIME_GATEWAY_GAS_LIMIT,
+            Self::Contracts => CONTRACTS_GATEWAY_GAS_LIMIT,
+        }
+    }
+}
+
+impl FromStr for ExecGateway {
+    type Err = anyhow::Error;
+
+    /// Parses the gateway from one of the names listed in [`crate_metadata::COMPOSABLE_GATEWAYS`].
+    fn from_str(input: &str) -> Result<Self> {
+        match crate_metadata::resolve_gateway(input)? {
+            "runtime-gateway" => Ok(Self::Runtime),
+            "contracts-gateway" => Ok(Self::Contracts),
+            name => unreachable!("gateway '{}' is listed but not supported", name),
+        }
+    }
+}
+
+impl Display for ExecGateway {
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
+        match self {
+            Self::Runtime => write!(f, "runtime-gateway"),
+            Self::Contracts => write!(f, "contracts-gateway"),
+        }
+    }
+}
+
+/// Fully resolved parameters of an `exec` entry of the composable schedule.
+struct ExecParams {
+    gateway: ExecGateway,
+    extrinsic_opts: ExtrinsicOpts,
+    requester: AccountId32,
+    target: AccountId32,
+    phase: u8,
+    value: u128,
+    gas: GasOpts,
+    data: HexData,
+}
+
+impl ExecParams {
+    /// Resolves the entry, falling back to the signer's account as requester and target, and to
+    /// the gas and value of the network profile.
+    fn new(
+        exec: &ComposableExecConfig,
+        suri: &str,
+        password: Option<&str>,
+        network: &NetworkConfig,
+    ) -> Result<Self> {
+        let gateway = exec.gateway.parse::<ExecGateway>()?;
+        let extrinsic_opts = ExtrinsicOpts {
+            url: exec.url.clone(),
+            suri: suri.to_string(),
+            password: password.map(ToString::to_string),
+        };
+        let requester = match &exec.requester {
+            Some(requester) => parse_account(requester).context("Invalid requester")?,
+            None => {
+                let pair = sr25519::Pair::from_string(suri, password)
+                    .map_err(|_| anyhow::anyhow!("Secret string error"))?;
+                AccountId32::from(pair.public())
+            }
+        };
+        let target = match &exec.target {
+            Some(target) => parse_account(target).context("Invalid target")?,
+            None => requester.clone(),
+        };
+        let data = exec
+            .data
+            .as_deref()
+            .unwrap_or("00")
+            .parse::<HexData>()
+            .context("Invalid hex encoded input data")?;
+        Ok(ExecParams {
+            gateway,
+            extrinsic_opts,
+            requester,
+            target,
+            phase: exec.phase.unwrap_or_default(),
+            value: exec.value.or(network.value).unwrap_or_default(),
+            gas: GasOpts::from_limit(exec.gas.or(network.gas)),
+            data,
+        })
+    }
+}
+
+/// Result of executing a single `exec` entry of the composable schedule.
+pub struct ComponentExecResult {
+    /// Name of the executed component.
+    pub compose: String,
+    /// Gateway the component was executed through, as configured in the schedule.
+    pub gateway: String,
+    /// The execution stamp emitted by the gateway, if the execution succeeded.
+    pub execution_stamp: Option<String>,
+    /// The error which made the execution fail.
+    pub error: Option<String>,
+}
+
+/// Result of executing all `exec` entries of the composable schedule.
+pub struct ComposableExecResult {
+    /// Results of the individual entries, in schedule order. Entries after a failed entry are
+    /// not executed and therefore not listed.
+    pub executions: Vec<ComponentExecResult>,
+    /// The number of entries in the `exec` section of the schedule.
+    pub total: usize,
+}
+
+impl ComposableExecResult {
+    /// Returns `true` if every entry of the schedule was executed successfully.
+    pub fn is_success(&self) -> bool {
+        self.executions.len() == self.total && self.executions.iter().all(|e| e.error.is_none())
+    }
+
+    /// Renders a table of the execution stamps of all executed components.
+    pub fn display(&self) -> String {
+        let width = self
+            .executions
+            .iter()
+            .map(|e| e.compose.len())
+            .max()
+            .unwrap_or_default()
+            .max("Component".len());
+        let mut out = format!(
+            "\n {:<width$}  {:<17}  {}\n",
+            "Component".bold(),
+            "Gateway".bold(),
+            "Execution stamp".bold(),
+            width = width
+        );
+        for execution in &self.executions {
+            let stamp = match (&execution.execution_stamp, &execution.error) {
+                (Some(stamp), _) => stamp.normal(),
+                (None, Some(err)) => format!("FAILED: {}", err).bright_red(),
+                (None, None) => "".normal(),
+            };
+            out.push_str(&format!(
+                " {:<width$}  {:<17}  {}\n",
+                execution.compose,
+                execution.gateway,
+                stamp,
+                width = width
+            ));
+        }
+        let skipped = self.total - self.executions.len();
+        if skipped > 0 {
+            out.push_str(&format!(
+                "\n{} remaining component(s) were not executed",
+                skipped
+            ));
+        }
+        out
+    }
+}
+
+/// Executes all entries of the `exec` section of the composable schedule, in schedule order.
+///
+/// Each component's Wasm is loaded from its build destination, see [`get_dest_wasm_path`], and
+/// dispatched through the gateway named in its entry. The execution stops at the first entry
+/// which fails, since later phases usually depend on the earlier ones.
+pub(crate) fn execute(
+    manifest_path: &ManifestPath,
+    suri: &str,
+    password: Option<&str>,
+    network: &NetworkConfig,
+) -> Result<ComposableExecResult> {
+    let crate_metadata = CrateMetadata::collect(manifest_path)?;
+    let composable_schedule = crate_metadata.composable_schedule()?;
+    let exec_schedule = match composable_schedule.exec {
+        Some(exec_schedule) if !exec_schedule.is_empty() => exec_schedule,
+        _ => anyhow::bail!("Nothing to execute. Empty exec key of composable metadata."),
+    };
+
+    let mut executions = Vec::with_capacity(exec_schedule.len());
+    for exec in &exec_schedule {
+        println!(
+            "{} {} {} {}",
+            "Executing component".bright_blue().bold(),
+            exec.compose.bright_blue().bold(),
+            "via".bright_blue(),
+            exec.gateway
+        );
+        let result = execute_component(&crate_metadata, exec, suri, password, network)
+            .context(format!("Executing component {}", exec.compose));
+        let failed = result.is_err();
+        let (execution_stamp, error) = match result {
+            Ok(stamp) => (Some(stamp), None),
+            Err(err) => (None, Some(format!("{:#}", err))),
+        };
+        executions.push(ComponentExecResult {
+            compose: exec.compose.clone(),
+            gateway: exec.gateway.clone(),
+            execution_stamp,
+            error,
+        });
+        if failed {
+            break;
+        }
+    }
+
+    Ok(ComposableExecResult {
+        executions,
+        total: exec_schedule.len(),
+    })
+}
+
+/// Executes a single component, returning the debug representation of its execution stamp.
+fn execute_component(
+    crate_metadata: &CrateMetadata,
+    exec: &ComposableExecConfig,
+    suri: &str,
+    password: Option<&str>,
+    network: &NetworkConfig,
+) -> Result<String> {
+    let params = ExecParams::new(exec, suri, password, network)?;
+    let wasm_path = get_dest_wasm_path(exec.compose.clone(), crate_metadata);
+    let code = fs::read(&wasm_path).context(format!(
+        "Failed to read {}, run composable-build first",
+        wasm_path.display()
+    ))?;
+    let fallback = params.gateway.fallback_gas_limit();
+    let gas_limit = params.gas.gateway_limit(fallback, |gas_limit| {
+        super::dry_run::gateway_call(
+            &params.extrinsic_opts,
+            params.target.clone(),
+            &code,
+            params.value,
+            gas_limit,
+            params.data.clone(),
+        )
+    })?;
+
+    let stamp = match params.gateway {
+        ExecGateway::Runtime => format!(
+            "{:?}",
+            super::execute_call(
+                &params.extrinsic_opts,
+                params.requester,
+                params.target,
+                params.phase,
+                &code,
+                params.value,
+                gas_limit,
+                params.data,
+            )?
+        ),
+        ExecGateway::Contracts => {
+            super::execute_contract_call(
+                &params.extrinsic_opts,
+                params.requester,
+                params.target,
+                params.phase,
+                &code,
+                params.value,
+                gas_limit,
+                params.data,
+            )?
+            .0
+        }
+    };
+    Ok(stamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExecGateway;
+    use crate::crate_metadata;
+
+    #[test]
+    fn gateways_are_parsed_with_and_without_suffix() {
+        assert_eq!(
+            "runtime".parse::<ExecGateway>().unwrap(),
+            ExecGateway::Runtime
+        );
+        assert_eq!(
+            "runtime-gateway".parse::<ExecGateway>().unwrap(),
+            ExecGateway::Runtime
+        );
+        assert_eq!(
+            "contracts-gateway".parse::<ExecGateway>().unwrap(),
+            ExecGateway::Contracts
+        );
+        assert!("evm".parse::<ExecGateway>().is_err());
+        for (name, _) in crate_metadata::COMPOSABLE_GATEWAYS {
+            assert_eq!(name.parse::<ExecGateway>().unwrap().to_string(), *name);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::composable_wat::WatContractsScanner;
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+use anyhow::Result;
+use colored::Colorize;
+
+/// Summary of a valid composable schedule.
+pub struct ComposableValidateResult {
+    /// All components in the order in which they are built, marking those in Wasm text format.
+    pub components: Vec<(String, bool)>,
+    /// The number of entries in the `deploy` section of the schedule.
+    pub deploy: usize,
+    /// The number of entries in the `exec` section of the schedule.
+    pub exec: usize,
+}
+
+impl ComposableValidateResult {
+    /// Renders the summary of the schedule.
+    pub fn display(&self) -> String {
+        let components = self
+            .components
+            .iter()
+            .map(|(name, wat)| {
+                if *wat {
+                    format!("{} (wat)", name)
+                } else {
+                    name.clone()
+                }
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{}\n  {} {}\n  {} {}\n  {} {}",
+            "Composable schedule is valid".green().bold(),
+            "Build order:".bold(),
+            components.join(", "),
+            "Deploy entries:".bold(),
+            self.deploy,
+            "Exec entries:".bold(),
+            self.exec
+        )
+    }
+}
+
+/// Checks the composable schedule in `[package.metadata]` without building anything.
+///
+/// Besides parsing the schedule this resolves the build order of the components and loads all
+/// components in Wasm text format, so missing source files are reported as well.
+pub(crate) fn execute(manifest_path: &ManifestPath) -> Result<ComposableValidateResult> {
+    let crate_metadata = CrateMetadata::collect(manifest_path)?;
+    let composable_schedule = crate_metadata.composable_schedule()?;
+    let contracts_scanner = WatContractsScanner::discover(&crate_metadata, &composable_schedule)?;
+
+    let components = composable_schedule
+        .execution_order()?
+        .into_iter()
+        .map(|compose| {
+            let wat = contracts_scanner.find_by_name(&compose).is_some();
+            (compose, wat)
+        })
+        .collect();
+    Ok(ComposableValidateResult {
+        components,
+        deploy: composable_schedule.deploy.map_or(0, |deploy| deploy.len()),
+        exec: composable_schedule.exec.map_or(0, |exec| exec.len()),
+    })
+}
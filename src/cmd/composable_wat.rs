@@ -0,0 +1,417 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Discovery of composable components written in the Wasm text format.
+//!
+//! Components are either declared as string constants in the sources of the crate's library, or
+//! live in standalone `.wat` / `.wasm` files which are referenced from the `sources` table of
+//! `[package.metadata]`:
+//!
+//! ```toml
+//! [package.metadata.sources]
+//! flipper = "components/flipper.wat"
+//! ```
+//!
+//! The keys of the `sources` table are the component names as written in the schedule.
+
+use crate::{
+    crate_metadata::{ComposableScheduleMetadata, CrateMetadata},
+    util,
+};
+use anyhow::{Context, Result};
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter, Result as DisplayResult},
+    fs,
+    path::{Path, PathBuf},
+};
+use syn::{visit::Visit, Expr, ExprLit, Ident, Lit, Type};
+
+/// Suffix of the constant names which hold a Wasm text format contract.
+const WAT_CONTRACT_SUFFIX: &str = "_WAT_CONTRACT";
+
+/// Scanner for Wasm text format contracts.
+///
+/// Developers can write their contract directly in WAT format by assigning it to a string constant
+/// or static, whose name is the name of the component (in any case) followed by `_WAT_CONTRACT`:
+///
+/// ```ignore
+/// pub static EXAMPLE_WAT_CONTRACT: &str = r#"
+///     (module
+///         (func (export "call"))
+///         (func (export "deploy"))
+///     )
+/// "#;
+/// ```
+///
+/// Out of the above example the scanner will recognize a contract named `example`. Any string
+/// literal is accepted, including raw strings with an arbitrary number of `#` delimiters.
+///
+/// Names of components are unique, declaring a component twice results in an error.
+#[derive(Debug, Default)]
+pub struct WatContractsScanner {
+    contracts: Vec<WatContract>,
+}
+
+/// Entry of a smart contract as Wasm text format, or as an already compiled Wasm binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatContract {
+    /// The lowercase name of the component.
+    pub name: String,
+    /// The Wasm text format or binary code of the contract.
+    pub code: Vec<u8>,
+    /// The source file the contract was found in.
+    pub source: PathBuf,
+    /// The line of the constant declaring the contract, `None` for standalone files.
+    pub line: Option<usize>,
+}
+
+impl WatContract {
+    /// Returns `true` if the code is a Wasm binary rather than Wasm text format.
+    pub fn is_binary(&self) -> bool {
+        self.code.starts_with(b"\0asm")
+    }
+}
+
+impl Display for WatContract {
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
+        match self.line {
+            Some(line) => write!(f, "{}:{}", self.source.display(), line),
+            None => write!(f, "{}", self.source.display()),
+        }
+    }
+}
+
+impl WatContractsScanner {
+    /// Discovers the Wasm text format contracts of a crate.
+    ///
+    /// All Rust files below the source root of the crate's library are scanned, and all files
+    /// referenced in the `sources` table of the composable schedule are loaded. Tests, examples,
+    /// benches and the build script are not scanned.
+    pub fn discover(
+        crate_metadata: &CrateMetadata,
+        schedule: &ComposableScheduleMetadata,
+    ) -> Result<Self> {
+        let source_dir = crate_metadata.manifest_path.absolute_directory()?;
+        let lib_dir = crate_metadata
+            .root_package
+            .targets
+            .iter()
+            .find(|target| target.kind.iter().any(|t| t == "cdylib"))
+            .and_then(|target| {
+                PathBuf::from(target.src_path.clone())
+                    .parent()
+                    .map(Path::to_path_buf)
+            })
+            .unwrap_or_else(|| source_dir.join("src"));
+        Self::discover_in(
+            &source_dir,
+            &lib_dir,
+            &crate_metadata.target_directory,
+            schedule.sources.as_ref(),
+        )
+    }
+
+    /// Scans the Rust files below `lib_dir` and loads the referenced `sources`, which are
+    /// relative to `source_dir`.
+    ///
+    /// Files which cannot be parsed, e.g. because they use syntax newer than the parser supports,
+    /// are logged and skipped.
+    fn discover_in(
+        source_dir: &Path,
+        lib_dir: &Path,
+        target_dir: &Path,
+        sources: Option<&BTreeMap<String, PathBuf>>,
+    ) -> Result<Self> {
+        let mut scanner = Self::default();
+
+        for path in util::collect_source_files(lib_dir, target_dir, &["rs"]) {
+            let text = fs::read_to_string(&path).context(format!(
+                "Reading composable contracts file {}",
+                path.display()
+            ))?;
+            let source = relative_to(&path, source_dir);
+            match syn::parse_file(&text) {
+                Ok(file) => scanner.scan_file(&file, source)?,
+                Err(err) => log::warn!(
+                    "Skipping composable contracts file {}, it cannot be parsed: {}",
+                    source.display(),
+                    err
+                ),
+            }
+        }
+
+        for (name, path) in sources.into_iter().flatten() {
+            let code = fs::read(source_dir.join(path)).context(format!(
+                "Reading source file {} of component {}",
+                path.display(),
+                name
+            ))?;
+            scanner.add(WatContract {
+                name: name.clone(),
+                code,
+                source: path.clone(),
+                line: None,
+            })?;
+        }
+        Ok(scanner)
+    }
+
+    /// Scans the supplied parsed Rust source for Wasm text format contracts.
+    ///
+    /// `source` is only used to describe the location of the found contracts.
+    fn scan_file(&mut self, file: &syn::File, source: &Path) -> Result<()> {
+        let mut visitor = WatContractVisitor {
+            source,
+            found: Vec::new(),
+        };
+        visitor.visit_file(file);
+
+        for contract in visitor.found {
+            self.add(contract)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a contract, unless a contract of the same name was found before.
+    fn add(&mut self, contract: WatContract) -> Result<()> {
+        if let Some(existing) = self.find_by_name(&contract.name) {
+            anyhow::bail!(
+                "WAT contract for component '{}' is declared twice: at {} and at {}",
+                contract.name,
+                existing,
+                contract
+            );
+        }
+        self.contracts.push(contract);
+        Ok(())
+    }
+
+    /// Returns the contract of the component with the supplied name.
+    pub fn find_by_name(&self, name: &str) -> Option<&WatContract> {
+        self.contracts.iter().find(|c| c.name == name)
+    }
+}
+
+/// Collects all `*_WAT_CONTRACT` string constants and statics, including those in inline modules.
+struct WatContractVisitor<'a> {
+    source: &'a Path,
+    found: Vec<WatContract>,
+}
+
+impl WatContractVisitor<'_> {
+    fn record(&mut self, ident: &Ident, ty: &Type, expr: &Expr) {
+        let name = ident.to_string();
+        let name = match name.strip_suffix(WAT_CONTRACT_SUFFIX) {
+            Some(name) if !name.is_empty() && is_str_reference(ty) => name,
+            _ => return,
+        };
+        if let Expr::Lit(ExprLit {
+            lit: Lit::Str(code),
+            ..
+        }) = expr
+        {
+            self.found.push(WatContract {
+                name: name.to_lowercase(),
+                code: code.value().into_bytes(),
+                source: self.source.to_path_buf(),
+                line: Some(ident.span().start().line),
+            })
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for WatContractVisitor<'_> {
+    fn visit_item_const(&mut self, item: &'ast syn::ItemConst) {
+        self.record(&item.ident, &item.ty, &item.expr);
+    }
+
+    fn visit_item_static(&mut self, item: &'ast syn::ItemStatic) {
+        self.record(&item.ident, &item.ty, &item.expr);
+    }
+}
+
+/// Returns `path` relative to `base`, or `path` itself if it is not located below `base`.
+fn relative_to<'a>(path: &'a Path, base: &Path) -> &'a Path {
+    path.strip_prefix(base).unwrap_or(path)
+}
+
+/// Returns `true` if the type is `&str`, with or without a lifetime.
+fn is_str_reference(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) if reference.mutability.is_none() => {
+            matches!(&*reference.elem, Type::Path(path) if path.path.is_ident("str"))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatContractsScanner;
+    use crate::util::tests::with_tmp_dir;
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    fn scan(text: &str) -> anyhow::Result<WatContractsScanner> {
+        let mut scanner = WatContractsScanner::default();
+        scanner.scan_file(&syn::parse_file(text)?, Path::new("lib.rs"))?;
+        Ok(scanner)
+    }
+
+    #[test]
+    fn finds_all_contracts_regardless_of_delimiter_and_visibility() {
+        let text = r####"
+            // FLIPPER_WAT_CONTRACT: &str = r#"(module)"#;
+            /// Docs mentioning "#; do not end the contract.
+            pub const FLIPPER_WAT_CONTRACT: &str = r#"(module (func (export "call")))"#;
+
+            pub(crate) static INCREMENTER_WAT_CONTRACT: &'static str = r##"
+                (module (func (export "deploy") (i32.const 0) drop)) ;; "#
+            "##;
+
+            mod nested {
+                static Plain_WAT_CONTRACT: &str = "(module)";
+            }
+
+            const NOT_A_CONTRACT: &str = "(module)";
+            const NUMBER_WAT_CONTRACT: u32 = 1;
+        "####;
+
+        let scanner = scan(text).unwrap();
+
+        let flipper = scanner.find_by_name("flipper").unwrap();
+        assert_eq!(flipper.code, br#"(module (func (export "call")))"#);
+        assert_eq!(flipper.line, Some(4));
+        assert_eq!(flipper.to_string(), "lib.rs:4");
+
+        let incrementer = scanner.find_by_name("incrementer").unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&incrementer.code).trim(),
+            r##"(module (func (export "deploy") (i32.const 0) drop)) ;; "#"##
+        );
+        assert_eq!(incrementer.line, Some(6));
+
+        assert_eq!(scanner.find_by_name("plain").unwrap().code, b"(module)");
+        assert!(scanner.find_by_name("not_a").is_none());
+        assert!(scanner.find_by_name("number").is_none());
+        assert_eq!(scanner.contracts.len(), 3);
+    }
+
+    #[test]
+    fn duplicate_contracts_are_rejected() {
+        let text = r#"
+            const FLIPPER_WAT_CONTRACT: &str = "(module)";
+            mod other {
+                const FLIPPER_WAT_CONTRACT: &str = "(module)";
+            }
+        "#;
+
+        let err = scan(text).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "WAT contract for component 'flipper' is declared twice: at lib.rs:2 and at lib.rs:4"
+        );
+    }
+
+    #[test]
+    fn discovers_contracts_in_lib_sources_and_referenced_files() {
+        with_tmp_dir(|path| {
+            // given
+            let target_dir = path.join("target");
+            fs::create_dir_all(path.join("src/components"))?;
+            fs::create_dir_all(&target_dir)?;
+            fs::write(
+                path.join("src/lib.rs"),
+                "const FLIPPER_WAT_CONTRACT: &str = \"(module)\";",
+            )?;
+            fs::create_dir_all(path.join("tests"))?;
+            fs::write(
+                path.join("tests/integration.rs"),
+                "const TESTED_WAT_CONTRACT: &str = \"(module)\";",
+            )?;
+            fs::write(path.join("build.rs"), "fn main() {")?;
+            fs::write(path.join("src/unparsable.rs"), "const FLIPPER_WAT_CONTRACT")?;
+            fs::write(
+                path.join("src/components/mod.rs"),
+                "\n\npub const INCREMENTER_WAT_CONTRACT: &str = \"(module)\";",
+            )?;
+            fs::write(
+                target_dir.join("ignored.rs"),
+                "const IGNORED_WAT_CONTRACT: &str = \"(module)\";",
+            )?;
+            fs::write(path.join("erc20.wat"), "(module)")?;
+            fs::write(path.join("adder.wasm"), b"\0asm\x01\0\0\0")?;
+            let sources = vec![
+                ("erc20".to_string(), PathBuf::from("erc20.wat")),
+                ("Adder".to_string(), PathBuf::from("adder.wasm")),
+            ]
+            .into_iter()
+            .collect();
+
+            // when
+            let scanner = WatContractsScanner::discover_in(
+                path,
+                &path.join("src"),
+                &target_dir,
+                Some(&sources),
+            )?;
+
+            // then
+            let incrementer = scanner.find_by_name("incrementer").unwrap();
+            assert_eq!(incrementer.to_string(), "src/components/mod.rs:3");
+            assert_eq!(
+                scanner.find_by_name("flipper").unwrap().to_string(),
+                "src/lib.rs:1"
+            );
+            let erc20 = scanner.find_by_name("erc20").unwrap();
+            assert_eq!(erc20.to_string(), "erc20.wat");
+            assert!(!erc20.is_binary());
+            assert!(scanner.find_by_name("Adder").unwrap().is_binary());
+            assert!(scanner.find_by_name("adder").is_none());
+            assert!(scanner.find_by_name("ignored").is_none());
+            assert!(scanner.find_by_name("tested").is_none());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn missing_referenced_file_is_reported() {
+        with_tmp_dir(|path| {
+            let sources = vec![("erc20".to_string(), PathBuf::from("erc20.wat"))]
+                .into_iter()
+                .collect();
+
+            let result = WatContractsScanner::discover_in(
+                path,
+                &path.join("src"),
+                &path.join("target"),
+                Some(&sources),
+            );
+
+            assert!(result.is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn invalid_source_is_reported() {
+        assert!(scan("const FLIPPER_WAT_CONTRACT: &str = ").is_err());
+    }
+}
@@ -0,0 +1,504 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Typed view of the ABI section of ink! contract metadata.
+//!
+//! Both the plain ABI layout and the layout wrapped in a version key (e.g. `"V1": { .. }`) are
+//! supported.
+
+#[cfg(feature = "extrinsics")]
+use super::transcode::{self, TypeRegistry};
+use crate::crate_metadata::CrateMetadata;
+use anyhow::{Context, Result};
+use contract_metadata::ContractMetadata;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Loads a `<name>.contract` bundle or a `metadata.json` file.
+pub fn load_metadata(path: &Path) -> Result<ContractMetadata> {
+    let contents = fs::read(path).context(format!("Reading {}", path.display()))?;
+    serde_json::from_slice(&contents)
+        .context(format!("Parsing contract metadata {}", path.display()))
+}
+
+/// Returns `true` if the path points to a `<name>.contract` bundle rather than a Wasm file.
+pub fn is_bundle(path: &Path) -> bool {
+    matches!(path.extension(), Some(extension) if extension == "contract")
+}
+
+/// Load the wasm blob from the specified path, which is either a Wasm file or a
+/// `<name>.contract` bundle.
+///
+/// Defaults to the target contract wasm in the current project, inferred via the crate metadata.
+pub fn load_contract_code(path: Option<&PathBuf>) -> Result<Vec<u8>> {
+    if let Some(bundle) = path.filter(|path| is_bundle(path)) {
+        let metadata = load_metadata(bundle)?;
+        let wasm = metadata.source().wasm().context(format!(
+            "{} does not include the Wasm code",
+            bundle.display()
+        ))?;
+        return Ok(wasm.as_bytes().to_vec());
+    }
+
+    let contract_wasm_path = match path {
+        Some(path) => path.clone(),
+        None => {
+            let metadata = CrateMetadata::collect(&Default::default())?;
+            metadata.dest_wasm
+        }
+    };
+    log::info!("Contract code path: {}", contract_wasm_path.display());
+    let mut data = Vec::new();
+    let mut file = fs::File::open(&contract_wasm_path)
+        .context(format!("Failed to open {}", contract_wasm_path.display()))?;
+    file.read_to_end(&mut data)?;
+
+    Ok(data)
+}
+
+/// The constructors, messages and events of a contract.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContractSpec {
+    #[serde(default)]
+    pub constructors: Vec<MessageSpec>,
+    #[serde(default)]
+    pub messages: Vec<MessageSpec>,
+    #[serde(default)]
+    pub events: Vec<EventSpec>,
+}
+
+/// A constructor or message of a contract.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSpec {
+    #[serde(alias = "label", deserialize_with = "deserialize_path")]
+    pub name: String,
+    /// The hex encoded selector, e.g. `0x633aa551`.
+    pub selector: String,
+    /// Whether the message mutates the contract storage. Always `false` for constructors.
+    #[serde(default)]
+    pub mutates: bool,
+    #[serde(default)]
+    pub payable: bool,
+    #[serde(default)]
+    pub args: Vec<ArgSpec>,
+    #[serde(default)]
+    pub return_type: Option<TypeSpec>,
+}
+
+impl MessageSpec {
+    /// Renders the signature of the message, e.g. `(value: bool) -> u32`.
+    pub fn signature(&self) -> String {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| format!("{}: {}", arg.name, arg.ty))
+            .collect::<Vec<_>>();
+        match &self.return_type {
+            Some(return_type) => format!("({}) -> {}", args.join(", "), return_type),
+            None => format!("({})", args.join(", ")),
+        }
+    }
+}
+
+/// An argument of a constructor or message.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArgSpec {
+    #[serde(alias = "label", deserialize_with = "deserialize_path")]
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: TypeSpec,
+}
+
+/// An event emitted by a contract.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventSpec {
+    #[serde(alias = "label", deserialize_with = "deserialize_path")]
+    pub name: String,
+    #[serde(default)]
+    pub args: Vec<EventArgSpec>,
+}
+
+/// A field of an event.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventArgSpec {
+    #[serde(alias = "label", deserialize_with = "deserialize_path")]
+    pub name: String,
+    #[serde(default)]
+    pub indexed: bool,
+    #[serde(rename = "type")]
+    pub ty: TypeSpec,
+}
+
+/// Reference to a type of the type registry, along with the name it is displayed with.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeSpec {
+    #[serde(rename = "type")]
+    pub id: u32,
+    #[serde(default)]
+    pub display_name: Vec<String>,
+}
+
+impl std::fmt::Display for TypeSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.display_name.is_empty() {
+            write!(f, "<type {}>", self.id)
+        } else {
+            write!(f, "{}", self.display_name.join("::"))
+        }
+    }
+}
+
+/// The ABI section of the contract metadata.
+#[derive(Debug, Clone)]
+pub struct ContractAbi {
+    pub spec: ContractSpec,
+    #[cfg(feature = "extrinsics")]
+    pub types: TypeRegistry,
+}
+
+impl ContractAbi {
+    /// Extracts the ABI from the raw ABI section of the contract metadata.
+    pub fn new(abi: &Map<String, Value>) -> Result<Self> {
+        // Newer ink! versions wrap the ABI in a key naming the version of its layout.
+        let abi = match abi.get("spec") {
+            Some(_) => abi,
+            None => abi
+                .values()
+                .filter_map(Value::as_object)
+                .find(|versioned| versioned.contains_key("spec"))
+                .context("The contract metadata contains no ABI spec")?,
+        };
+        let spec = serde_json::from_value(abi["spec"].clone()).context("Invalid ABI spec")?;
+        Ok(ContractAbi {
+            spec,
+            #[cfg(feature = "extrinsics")]
+            types: match abi.get("types") {
+                Some(Value::Array(types)) => TypeRegistry::new(types.clone()),
+                _ => TypeRegistry::default(),
+            },
+        })
+    }
+}
+
+#[cfg(feature = "extrinsics")]
+impl ContractAbi {
+    /// Loads the ABI from a `<name>.contract` bundle or a `metadata.json` file.
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::new(load_metadata(path)?.abi())
+    }
+
+    /// Encodes the input data of a call to the message `name`, i.e. its selector followed by the
+    /// SCALE encoded `args`.
+    pub fn encode_message(&self, name: &str, args: &[String]) -> Result<Vec<u8>> {
+        encode_call("message", &self.spec.messages, name, args, &self.types)
+    }
+
+    /// Encodes the input data of the constructor `name`, i.e. its selector followed by the SCALE
+    /// encoded `args`.
+    pub fn encode_constructor(&self, name: &str, args: &[String]) -> Result<Vec<u8>> {
+        encode_call(
+            "constructor",
+            &self.spec.constructors,
+            name,
+            args,
+            &self.types,
+        )
+    }
+
+    /// Decodes the SCALE encoded value returned by the message `name`.
+    ///
+    /// Messages without return type decode to `null`.
+    pub fn decode_return(&self, name: &str, data: &[u8]) -> Result<Value> {
+        let message = find_spec("message", &self.spec.messages, name)?;
+        let return_type = match &message.return_type {
+            Some(return_type) => return_type,
+            None => return Ok(Value::Null),
+        };
+        let mut input = data;
+        let value = self
+            .types
+            .decode_value(return_type.id, &mut input)
+            .context(format!("Decoding the return value of {}", message.name))?;
+        if !input.is_empty() {
+            anyhow::bail!(
+                "{} bytes left over after decoding the return value of {}",
+                input.len(),
+                message.name
+            );
+        }
+        Ok(value)
+    }
+
+    /// Decodes the data of an event emitted by the contract, i.e. the index of the event followed
+    /// by its SCALE encoded fields.
+    pub fn decode_event(&self, data: &[u8]) -> Result<DecodedEvent> {
+        let (index, mut input) = data.split_first().context("The event data is empty")?;
+        let event = self.spec.events.get(*index as usize).context(format!(
+            "No event with index {} in the contract metadata",
+            index
+        ))?;
+        let mut fields = Map::new();
+        for arg in &event.args {
+            let value = self
+                .types
+                .decode_value(arg.ty.id, &mut input)
+                .context(format!(
+                    "Decoding field {} of event {}",
+                    arg.name, event.name
+                ))?;
+            fields.insert(arg.name.clone(), value);
+        }
+        if !input.is_empty() {
+            anyhow::bail!(
+                "{} bytes left over after decoding event {}",
+                input.len(),
+                event.name
+            );
+        }
+        Ok(DecodedEvent {
+            name: event.name.clone(),
+            fields,
+        })
+    }
+}
+
+/// An event emitted by the contract, decoded against the contract metadata.
+#[cfg(feature = "extrinsics")]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DecodedEvent {
+    pub name: String,
+    pub fields: Map<String, Value>,
+}
+
+#[cfg(feature = "extrinsics")]
+impl std::fmt::Display for DecodedEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect::<Vec<_>>();
+        write!(f, "{} {{ {} }}", self.name, fields.join(", "))
+    }
+}
+
+/// Finds the constructor or message `name`, listing the available ones if there is none.
+#[cfg(feature = "extrinsics")]
+fn find_spec<'a>(kind: &str, specs: &'a [MessageSpec], name: &str) -> Result<&'a MessageSpec> {
+    match specs.iter().find(|spec| spec.name == name) {
+        Some(spec) => Ok(spec),
+        None => {
+            let available = specs
+                .iter()
+                .map(|spec| format!("\n  {}{}", spec.name, spec.signature()))
+                .collect::<String>();
+            anyhow::bail!(
+                "No {} '{}' in the contract metadata, available {}s are:{}",
+                kind,
+                name,
+                kind,
+                available
+            )
+        }
+    }
+}
+
+#[cfg(feature = "extrinsics")]
+fn encode_call(
+    kind: &str,
+    specs: &[MessageSpec],
+    name: &str,
+    args: &[String],
+    types: &TypeRegistry,
+) -> Result<Vec<u8>> {
+    let spec = find_spec(kind, specs, name)?;
+    if spec.args.len() != args.len() {
+        anyhow::bail!(
+            "The {} {}{} expects {} argument(s), got {}",
+            kind,
+            spec.name,
+            spec.signature(),
+            spec.args.len(),
+            args.len()
+        );
+    }
+    let mut data = transcode::hex_bytes(&spec.selector)
+        .context(format!("Invalid selector of {} {}", kind, spec.name))?;
+    for (arg_spec, arg) in spec.args.iter().zip(args) {
+        let encoded = types.encode_arg(arg_spec.ty.id, arg).context(format!(
+            "Encoding argument {}: {}",
+            arg_spec.name, arg_spec.ty
+        ))?;
+        data.extend(encoded);
+    }
+    Ok(data)
+}
+
+/// Deserializes a name which is either a plain string or a path of segments.
+fn deserialize_path<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NameOrPath {
+        Name(String),
+        Path(Vec<String>),
+    }
+    Ok(match NameOrPath::deserialize(deserializer)? {
+        NameOrPath::Name(name) => name,
+        NameOrPath::Path(path) => path.join("::"),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::ContractAbi;
+    use serde_json::{json, Map, Value};
+
+    /// ABI section of a flipper contract, in the layout of ink! 3.0.0-rc5.
+    pub(crate) fn flipper_abi() -> Map<String, Value> {
+        let abi = json!({
+            "V1": {
+                "spec": {
+                    "constructors": [
+                        {
+                            "name": ["new"],
+                            "selector": "0x9bae9d5e",
+                            "args": [{ "name": "init_value", "type": { "displayName": ["bool"], "type": 1 } }],
+                            "docs": []
+                        },
+                        { "name": ["default"], "selector": "0xed4b9d1b", "args": [], "docs": [] }
+                    ],
+                    "messages": [
+                        {
+                            "name": ["flip"],
+                            "selector": "0x633aa551",
+                            "mutates": true,
+                            "payable": false,
+                            "args": [],
+                            "returnType": null,
+                            "docs": []
+                        },
+                        {
+                            "name": ["get"],
+                            "selector": "0x2f865bd9",
+                            "mutates": false,
+                            "payable": false,
+                            "args": [],
+                            "returnType": { "displayName": ["bool"], "type": 1 },
+                            "docs": []
+                        }
+                    ],
+                    "events": [
+                        {
+                            "name": "Flipped",
+                            "args": [{ "name": "value", "indexed": true, "type": { "displayName": ["bool"], "type": 1 } }],
+                            "docs": []
+                        }
+                    ],
+                    "docs": []
+                },
+                "storage": {},
+                "types": [{ "def": { "primitive": "bool" } }]
+            }
+        });
+        abi.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn versioned_abi_is_parsed() {
+        let abi = ContractAbi::new(&flipper_abi()).unwrap();
+
+        let constructors = &abi.spec.constructors;
+        assert_eq!(constructors.len(), 2);
+        assert_eq!(constructors[0].name, "new");
+        assert_eq!(constructors[0].signature(), "(init_value: bool)");
+        let get = &abi.spec.messages[1];
+        assert_eq!(get.selector, "0x2f865bd9");
+        assert!(!get.mutates);
+        assert_eq!(get.signature(), "() -> bool");
+        assert_eq!(abi.spec.events[0].name, "Flipped");
+    }
+
+    #[cfg(feature = "extrinsics")]
+    #[test]
+    fn calls_are_encoded_with_their_selector() {
+        let abi = ContractAbi::new(&flipper_abi()).unwrap();
+
+        assert_eq!(
+            abi.encode_constructor("new", &["true".to_string()])
+                .unwrap(),
+            vec![0x9b, 0xae, 0x9d, 0x5e, 1]
+        );
+        assert_eq!(
+            abi.encode_message("flip", &[]).unwrap(),
+            vec![0x63, 0x3a, 0xa5, 0x51]
+        );
+        assert!(abi.encode_message("flip", &["true".to_string()]).is_err());
+    }
+
+    #[cfg(feature = "extrinsics")]
+    #[test]
+    fn unknown_messages_list_the_available_ones() {
+        let abi = ContractAbi::new(&flipper_abi()).unwrap();
+
+        let err = abi.encode_message("flp", &[]).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "No message 'flp' in the contract metadata, available messages are:\n  flip()\n  get() -> bool"
+        );
+    }
+
+    #[cfg(feature = "extrinsics")]
+    #[test]
+    fn return_values_are_decoded() {
+        let abi = ContractAbi::new(&flipper_abi()).unwrap();
+
+        assert_eq!(abi.decode_return("get", &[1]).unwrap(), json!(true));
+        assert_eq!(abi.decode_return("flip", &[]).unwrap(), json!(null));
+        assert!(abi.decode_return("get", &[1, 0]).is_err());
+    }
+
+    #[cfg(feature = "extrinsics")]
+    #[test]
+    fn events_are_decoded_by_index() {
+        let abi = ContractAbi::new(&flipper_abi()).unwrap();
+
+        let event = abi.decode_event(&[0, 1]).unwrap();
+
+        assert_eq!(event.to_string(), "Flipped { value: true }");
+        assert!(abi.decode_event(&[1, 1]).is_err());
+        assert!(abi.decode_event(&[0, 1, 0]).is_err());
+    }
+
+    #[test]
+    fn unversioned_abi_is_parsed() {
+        let abi = flipper_abi()["V1"].as_object().unwrap().clone();
+
+        let abi = ContractAbi::new(&abi).unwrap();
+
+        assert_eq!(abi.spec.messages[0].name, "flip");
+    }
+}
@@ -14,33 +14,15 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{fs, io::Read, path::PathBuf};
+use std::path::PathBuf;
 
 use anyhow::{Context, Result};
-use sp_core::H256;
+use serde_json::Value;
+use sp_core::{hashing::twox_128, H256};
 use subxt::{contracts::*, ClientBuilder, ContractsTemplateRuntime};
 
-use crate::{crate_metadata, ExtrinsicOpts};
-
-/// Load the wasm blob from the specified path.
-///
-/// Defaults to the target contract wasm in the current project, inferred via the crate metadata.
-pub fn load_contract_code(path: Option<&PathBuf>) -> Result<Vec<u8>> {
-    let contract_wasm_path = match path {
-        Some(path) => path.clone(),
-        None => {
-            let metadata = crate_metadata::CrateMetadata::collect(&Default::default())?;
-            metadata.dest_wasm
-        }
-    };
-    log::info!("Contract code path: {}", contract_wasm_path.display());
-    let mut data = Vec::new();
-    let mut file = fs::File::open(&contract_wasm_path)
-        .context(format!("Failed to open {}", contract_wasm_path.display()))?;
-    file.read_to_end(&mut data)?;
-
-    Ok(data)
-}
+use super::{contract_abi::load_contract_code, dry_run::rpc_request, metadata::blake2_hash};
+use crate::ExtrinsicOpts;
 
 /// Put contract code to a smart contract enabled substrate chain.
 /// Returns the code hash of the deployed contract if successful.
@@ -55,7 +37,47 @@ pub(crate) fn execute_deploy(
     contract_wasm_path: Option<&PathBuf>,
 ) -> Result<H256> {
     let code = load_contract_code(contract_wasm_path)?;
+    upload_code(extrinsic_opts, &code)
+}
+
+/// Result of [`deploy_unless_stored`].
+pub(crate) struct DeployedCode {
+    pub code_hash: H256,
+    /// Whether the code was uploaded, `false` if it was already stored on chain.
+    pub uploaded: bool,
+}
+
+/// Uploads the code unless code with the same hash is already stored on chain.
+pub(crate) fn deploy_unless_stored(
+    extrinsic_opts: &ExtrinsicOpts,
+    code: &[u8],
+) -> Result<DeployedCode> {
+    let code_hash = H256(blake2_hash(code).0);
+    if is_code_stored(extrinsic_opts, code_hash)? {
+        return Ok(DeployedCode {
+            code_hash,
+            uploaded: false,
+        });
+    }
+    Ok(DeployedCode {
+        code_hash: upload_code(extrinsic_opts, code)?,
+        uploaded: true,
+    })
+}
+
+/// Checks whether code with the supplied hash is stored in `Contracts::PristineCode`.
+fn is_code_stored(extrinsic_opts: &ExtrinsicOpts, code_hash: H256) -> Result<bool> {
+    let mut key = twox_128(b"Contracts").to_vec();
+    key.extend_from_slice(&twox_128(b"PristineCode"));
+    // The map is keyed with the identity hasher.
+    key.extend_from_slice(code_hash.as_bytes());
+    let storage_key = Value::String(format!("0x{}", hex::encode(key)));
+    let stored = rpc_request(extrinsic_opts, "state_getStorageHash", vec![storage_key])?;
+    Ok(!stored.is_null())
+}
 
+/// Uploads the code via `Contracts::put_code`, returning its code hash.
+fn upload_code(extrinsic_opts: &ExtrinsicOpts, code: &[u8]) -> Result<H256> {
     async_std::task::block_on(async move {
         let cli = ClientBuilder::<ContractsTemplateRuntime>::new()
             .set_url(&extrinsic_opts.url.to_string())
@@ -63,7 +85,7 @@ pub(crate) fn execute_deploy(
             .await?;
         let signer = extrinsic_opts.signer()?;
 
-        let events = cli.put_code_and_watch(&signer, &code).await?;
+        let events = cli.put_code_and_watch(&signer, code).await?;
         let code_stored = events
             .code_stored()?
             .context("Failed to find CodeStored event")?;
@@ -0,0 +1,426 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Dry runs of contract calls and instantiations via the contracts RPC of the node.
+//!
+//! The node executes the call on top of its best block and discards all changes, so no
+//! transaction is submitted and no fees are paid.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::{json, Value};
+use sp_core::{crypto::Pair, sr25519, H256};
+use subxt::{system::System, ContractsTemplateRuntime};
+
+use super::transcode::hex_bytes;
+use crate::{ExtrinsicOpts, HexData};
+
+type AccountId = <ContractsTemplateRuntime as System>::AccountId;
+
+/// Flag set in the `flags` of the return value if the contract reverted the call.
+const FLAG_REVERT: u64 = 1;
+
+/// Gas limit of dry runs without a supplied limit, the maximum accepted by the contracts RPC.
+pub(crate) const ESTIMATION_GAS_LIMIT: u64 = 5_000_000_000_000;
+
+/// Result of a dry run, as returned by the `contracts_call` or `contracts_instantiate` RPC.
+#[derive(Debug, Default, serde::Serialize)]
+pub(crate) struct DryRunResult {
+    /// Whether the call completed without trapping and without reverting.
+    pub success: bool,
+    /// Whether the contract reverted the call, its state changes would be rolled back.
+    pub reverted: bool,
+    /// The dispatch error, if the call trapped or could not be executed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+    /// The hex encoded data returned by the contract.
+    pub data: String,
+    /// The return value decoded against the contract metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_value: Option<Value>,
+    /// The account of the contract, for instantiations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_consumed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_required: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_deposit: Option<Value>,
+    /// Output of `ink_env::debug_println!` and friends, if the node records it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug_message: Option<String>,
+}
+
+impl DryRunResult {
+    /// Reads the result of the RPC.
+    ///
+    /// The layout of the result changed between versions of the contracts pallet, all of them
+    /// are supported: `{"success": {..}}` of Substrate 2.0, as well as `{"result": {"Ok": {..}}}`
+    /// or `{"execResult": {"ok": {..}}}` of later versions.
+    pub fn from_rpc(result: &Value) -> Result<Self> {
+        let object = result
+            .as_object()
+            .context(format!("Unexpected dry run result {}", result))?;
+        let mut dry_run = DryRunResult {
+            gas_consumed: find_u64(result, "gasConsumed"),
+            gas_required: find_u64(result, "gasRequired"),
+            storage_deposit: find(result, "storageDeposit").cloned(),
+            debug_message: find(result, "debugMessage")
+                .and_then(Value::as_str)
+                .map(debug_message)
+                .filter(|message| !message.is_empty()),
+            ..Default::default()
+        };
+
+        let outcome = object
+            .get("result")
+            .or_else(|| object.get("execResult"))
+            .unwrap_or(result);
+        let ok = get_ignore_case(outcome, "ok").or_else(|| get_ignore_case(outcome, "success"));
+        match (
+            ok,
+            get_ignore_case(outcome, "err").or_else(|| get_ignore_case(outcome, "error")),
+        ) {
+            (Some(ok), _) => {
+                // Instantiations nest the return value of the constructor in `result`.
+                let exec_result = ok.get("result").unwrap_or(ok);
+                let flags = find_u64(exec_result, "flags").unwrap_or_default();
+                dry_run.reverted = flags & FLAG_REVERT != 0;
+                dry_run.success = !dry_run.reverted;
+                dry_run.data = find(exec_result, "data")
+                    .and_then(Value::as_str)
+                    .unwrap_or("0x")
+                    .to_string();
+                dry_run.account_id = find(ok, "accountId")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                if dry_run.gas_consumed.is_none() {
+                    dry_run.gas_consumed = find_u64(ok, "gas_consumed");
+                }
+            }
+            (None, Some(err)) => {
+                dry_run.error = Some(err.clone());
+                dry_run.data = "0x".to_string();
+            }
+            (None, None) => anyhow::bail!("Unexpected dry run result {}", result),
+        }
+        Ok(dry_run)
+    }
+
+    /// The bytes returned by the contract.
+    pub fn data(&self) -> Result<Vec<u8>> {
+        hex_bytes(&self.data)
+    }
+
+    /// Returns the result serialized as pretty-printed JSON.
+    pub fn serialize_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Renders the outcome, the return value and the resources the call would consume.
+    pub fn display(&self) -> String {
+        let outcome = match (&self.error, self.reverted) {
+            (Some(error), _) => format!("{} {}", "failed:".bright_red().bold(), error),
+            (None, true) => "reverted".yellow().bold().to_string(),
+            (None, false) => "success".green().bold().to_string(),
+        };
+        let mut out = format!(
+            "{} {}\n",
+            "Dry run (nothing was submitted):".bright_blue().bold(),
+            outcome
+        );
+        if let Some(account_id) = &self.account_id {
+            out.push_str(&format!("  contract:        {}\n", account_id));
+        }
+        match &self.return_value {
+            Some(return_value) => out.push_str(&format!("  return value:    {}\n", return_value)),
+            None if self.error.is_none() => {
+                out.push_str(&format!("  return data:     {}\n", self.data))
+            }
+            None => (),
+        }
+        if let Some(gas_consumed) = self.gas_consumed {
+            out.push_str(&format!("  gas consumed:    {}\n", gas_consumed));
+        }
+        if let Some(gas_required) = self.gas_required {
+            out.push_str(&format!("  gas required:    {}\n", gas_required));
+        }
+        if let Some(storage_deposit) = &self.storage_deposit {
+            out.push_str(&format!("  storage deposit: {}\n", storage_deposit));
+        }
+        if let Some(debug_message) = &self.debug_message {
+            out.push_str(&format!(
+                "  debug messages:\n    {}\n",
+                debug_message.replace('\n', "\n    ")
+            ));
+        }
+        out
+    }
+}
+
+/// Estimates the gas limit of a call from its dry run, adding `margin_percent` to the gas
+/// required by the call.
+pub(crate) fn estimate_gas(dry_run: &DryRunResult, margin_percent: u64) -> Result<u64> {
+    if let Some(error) = &dry_run.error {
+        anyhow::bail!(
+            "The dry run to estimate the gas failed: {}, pass --gas to submit anyway",
+            error
+        );
+    }
+    if dry_run.reverted {
+        anyhow::bail!("The dry run to estimate the gas was reverted, pass --gas to submit anyway");
+    }
+    let required = dry_run
+        .gas_required
+        .or(dry_run.gas_consumed)
+        .context("The node did not report the gas consumed by the dry run, pass --gas")?;
+    Ok(required.saturating_add(required.saturating_mul(margin_percent) / 100))
+}
+
+/// Dry runs a call of the contract at `dest`.
+pub(crate) fn call(
+    extrinsic_opts: &ExtrinsicOpts,
+    dest: AccountId,
+    value: u128,
+    gas_limit: u64,
+    data: HexData,
+) -> Result<DryRunResult> {
+    let request = json!({
+        "origin": origin(extrinsic_opts)?.to_string(),
+        "dest": dest.to_string(),
+        "value": value_param(value),
+        "gasLimit": gas_limit,
+        "inputData": format!("0x{}", hex::encode(&data.0)),
+    });
+    let result = rpc_request(extrinsic_opts, "contracts_call", vec![request])?;
+    DryRunResult::from_rpc(&result)
+}
+
+/// Code of a contract to instantiate.
+pub(crate) enum InstantiateCode<'a> {
+    /// Code already stored on chain.
+    Existing(H256),
+    /// Code which would be uploaded along with the instantiation.
+    Upload(&'a [u8]),
+}
+
+/// Dry runs the instantiation of the supplied code.
+pub(crate) fn instantiate(
+    extrinsic_opts: &ExtrinsicOpts,
+    endowment: u128,
+    gas_limit: u64,
+    code: InstantiateCode,
+    data: HexData,
+    salt: &[u8],
+) -> Result<DryRunResult> {
+    let code = match code {
+        InstantiateCode::Existing(code_hash) => json!({ "existing": format!("{:?}", code_hash) }),
+        InstantiateCode::Upload(code) => json!({ "upload": format!("0x{}", hex::encode(code)) }),
+    };
+    let request = json!({
+        "origin": origin(extrinsic_opts)?.to_string(),
+        "endowment": value_param(endowment),
+        "gasLimit": gas_limit,
+        "code": code,
+        "data": format!("0x{}", hex::encode(&data.0)),
+        "salt": format!("0x{}", hex::encode(salt)),
+    });
+    let result = rpc_request(extrinsic_opts, "contracts_instantiate", vec![request])?;
+    DryRunResult::from_rpc(&result)
+}
+
+/// Dry runs the execution of `code` via one of the gateways, to estimate its gas.
+///
+/// There is no RPC to dry run the execution path of the gateways, so this is an approximation:
+/// the code is dry run via the contracts RPC from the signer's origin, as an instantiation of
+/// the code with the input `data`, or as a call of the contract at `target` if no code is
+/// supplied. Nodes without the contracts RPC cannot dry run the execution, see
+/// [`is_rpc_unavailable`].
+pub(crate) fn gateway_call(
+    extrinsic_opts: &ExtrinsicOpts,
+    target: AccountId,
+    code: &[u8],
+    value: u128,
+    gas_limit: u64,
+    data: HexData,
+) -> Result<DryRunResult> {
+    match code.is_empty() {
+        true => call(extrinsic_opts, target, value, gas_limit, data),
+        false => instantiate(
+            extrinsic_opts,
+            value,
+            gas_limit,
+            InstantiateCode::Upload(code),
+            data,
+            &[],
+        ),
+    }
+}
+
+/// Returns `true` if `err` is caused by the node not providing the requested RPC, e.g.
+/// `contracts_instantiate` on Substrate 2.0 nodes.
+pub(crate) fn is_rpc_unavailable(err: &anyhow::Error) -> bool {
+    let message = format!("{:#}", err);
+    message.contains("Method not found") || message.contains("-32601")
+}
+
+/// The account of the signer, on whose behalf the call is executed.
+fn origin(extrinsic_opts: &ExtrinsicOpts) -> Result<AccountId> {
+    let pair = sr25519::Pair::from_string(
+        &extrinsic_opts.suri,
+        extrinsic_opts.password.as_ref().map(String::as_ref),
+    )
+    .map_err(|_| anyhow::anyhow!("Secret string error"))?;
+    Ok(AccountId::from(pair.public()))
+}
+
+/// Balances are passed as hex encoded numbers, since they may exceed the range of JSON numbers.
+fn value_param(value: u128) -> String {
+    format!("0x{:x}", value)
+}
+
+/// Sends a JSON-RPC request to the node.
+pub(crate) fn rpc_request(
+    extrinsic_opts: &ExtrinsicOpts,
+    method: &str,
+    params: Vec<Value>,
+) -> Result<Value> {
+    log::debug!("{} {:?}", method, params);
+    async_std::task::block_on(async move {
+        let client = jsonrpsee::ws_client(extrinsic_opts.url.as_str())
+            .await
+            .context(format!("Connecting to {}", extrinsic_opts.url))?;
+        let params = jsonrpsee::common::Params::Array(params);
+        let result: Value = client
+            .request(method, params)
+            .await
+            .context(format!("Calling the {} RPC", method))?;
+        log::debug!("{} result {}", method, result);
+        Ok(result)
+    })
+}
+
+/// Renders the debug message, which is either a string or the hex encoded bytes of one.
+fn debug_message(message: &str) -> String {
+    match message.strip_prefix("0x").map(hex::decode) {
+        Some(Ok(bytes)) => String::from_utf8_lossy(&bytes).into_owned(),
+        _ => message.to_string(),
+    }
+}
+
+fn get_ignore_case<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    value
+        .as_object()?
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value)
+}
+
+/// Finds the value of `key` in `value` or any of its nested objects.
+fn find<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    let object = value.as_object()?;
+    object
+        .get(key)
+        .or_else(|| object.values().find_map(|nested| find(nested, key)))
+}
+
+/// Finds a number, which may be hex encoded, see [`find`].
+fn find_u64(value: &Value, key: &str) -> Option<u64> {
+    match find(value, key)? {
+        Value::Number(number) => number.as_u64(),
+        Value::String(hex) => u64::from_str_radix(hex.trim_start_matches("0x"), 16).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_gas, DryRunResult};
+    use serde_json::json;
+
+    #[test]
+    fn substrate_2_call_results_are_read() {
+        let result = json!({ "success": { "flags": 0, "data": "0x01", "gas_consumed": 1234 } });
+
+        let dry_run = DryRunResult::from_rpc(&result).unwrap();
+
+        assert!(dry_run.success);
+        assert_eq!(dry_run.data().unwrap(), vec![1]);
+        assert_eq!(dry_run.gas_consumed, Some(1234));
+    }
+
+    #[test]
+    fn call_results_with_debug_messages_and_deposits_are_read() {
+        let result = json!({
+            "gasConsumed": 5000,
+            "gasRequired": 6000,
+            "storageDeposit": { "charge": "0x1000" },
+            "debugMessage": "0x68656c6c6f",
+            "result": { "Ok": { "flags": 1, "data": "0x" } }
+        });
+
+        let dry_run = DryRunResult::from_rpc(&result).unwrap();
+
+        assert!(!dry_run.success);
+        assert!(dry_run.reverted);
+        assert_eq!(dry_run.gas_consumed, Some(5000));
+        assert_eq!(dry_run.gas_required, Some(6000));
+        assert_eq!(dry_run.storage_deposit, Some(json!({ "charge": "0x1000" })));
+        assert_eq!(dry_run.debug_message.as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn gas_is_estimated_with_margin() {
+        let dry_run = DryRunResult {
+            success: true,
+            gas_consumed: Some(1_000),
+            ..Default::default()
+        };
+        let reverted = DryRunResult {
+            reverted: true,
+            gas_consumed: Some(1_000),
+            ..Default::default()
+        };
+
+        assert_eq!(estimate_gas(&dry_run, 10).unwrap(), 1_100);
+        assert_eq!(estimate_gas(&dry_run, 0).unwrap(), 1_000);
+        assert!(estimate_gas(&reverted, 10).is_err());
+    }
+
+    #[test]
+    fn failed_instantiations_are_read() {
+        let ok = json!({
+            "gasConsumed": 5000,
+            "result": { "ok": { "result": { "flags": 0, "data": "0x" }, "accountId": "5Fake" } }
+        });
+        let err = json!({
+            "gasConsumed": 5000,
+            "result": { "err": { "module": { "index": 7, "error": 5 } } }
+        });
+
+        let ok = DryRunResult::from_rpc(&ok).unwrap();
+        let err = DryRunResult::from_rpc(&err).unwrap();
+
+        assert!(ok.success);
+        assert_eq!(ok.account_id.as_deref(), Some("5Fake"));
+        assert!(!err.success);
+        assert_eq!(
+            err.error,
+            Some(json!({ "module": { "index": 7, "error": 5 } }))
+        );
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::contract_abi::{self, ContractAbi, MessageSpec};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use contract_metadata::ContractMetadata;
+use std::{fs, path::PathBuf};
+use structopt::StructOpt;
+
+/// Prints the contents of a contract bundle and optionally extracts its Wasm or metadata.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "inspect")]
+pub struct InspectCommand {
+    /// Path to the `<name>.contract` bundle or `metadata.json` to inspect
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+    /// Write the Wasm code embedded in the bundle to the supplied file
+    #[structopt(long, parse(from_os_str))]
+    extract_wasm: Option<PathBuf>,
+    /// Write the metadata of the bundle, without the Wasm code, to the supplied file
+    #[structopt(long, parse(from_os_str))]
+    extract_metadata: Option<PathBuf>,
+}
+
+impl InspectCommand {
+    pub fn exec(&self) -> Result<InspectResult> {
+        let mut metadata = contract_abi::load_metadata(&self.path)?;
+        let abi = ContractAbi::new(metadata.abi())
+            .context(format!("Reading the ABI of {}", self.path.display()))?;
+
+        if let Some(dest_wasm) = &self.extract_wasm {
+            let wasm = metadata.source().wasm().context(format!(
+                "{} does not include the Wasm code, only `<name>.contract` bundles do",
+                self.path.display()
+            ))?;
+            fs::write(dest_wasm, wasm.as_bytes())
+                .context(format!("Writing Wasm code to {}", dest_wasm.display()))?;
+        }
+        let wasm_size = metadata.source().wasm().map(|wasm| wasm.as_bytes().len());
+        if let Some(dest_metadata) = &self.extract_metadata {
+            metadata.remove_source_wasm_attribute();
+            let contents = serde_json::to_string_pretty(&metadata)?;
+            fs::write(dest_metadata, contents)
+                .context(format!("Writing metadata to {}", dest_metadata.display()))?;
+        }
+
+        Ok(InspectResult {
+            metadata,
+            abi,
+            wasm_size,
+            extracted: self
+                .extract_wasm
+                .iter()
+                .chain(&self.extract_metadata)
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
+/// Contents of an inspected contract bundle.
+pub struct InspectResult {
+    /// The metadata of the bundle, without the Wasm code if the metadata was extracted.
+    pub metadata: ContractMetadata,
+    /// The ABI of the contract.
+    pub abi: ContractAbi,
+    /// Size of the embedded Wasm code in bytes, if the bundle includes it.
+    pub wasm_size: Option<usize>,
+    /// Files the Wasm code or the metadata were extracted to.
+    pub extracted: Vec<PathBuf>,
+}
+
+impl InspectResult {
+    /// Renders the contract information along with its constructors and messages.
+    pub fn display(&self) -> String {
+        let contract = self.metadata.contract();
+        let source = self.metadata.source();
+        let mut out = format!(
+            "\n{} {}\n",
+            contract.name().bold(),
+            contract.version().to_string().bold()
+        );
+        if let Some(description) = contract.description() {
+            out.push_str(&format!(" {}\n", description));
+        }
+        out.push_str(&format!(" authors:   {}\n", contract.authors().join(", ")));
+        out.push_str(&format!(" language:  {}\n", source.language()));
+        out.push_str(&format!(" compiler:  {}\n", source.compiler()));
+        out.push_str(&format!(" code hash: {}\n", source.hash()));
+        match self.wasm_size {
+            Some(size) => out.push_str(&format!(" wasm size: {:.1}K\n", size as f64 / 1000.0)),
+            None => out.push_str(" wasm size: not included\n"),
+        }
+        if let Some(build_info) = source.build_info() {
+            out.push_str(&format!(
+                " built with cargo-contract {}, {} mode, optimization passes {}\n",
+                build_info.cargo_contract_version,
+                build_info.build_mode,
+                build_info.optimization_passes
+            ));
+        }
+
+        out.push_str(&format!("\n{}\n", "Constructors".bright_blue().bold()));
+        for constructor in &self.abi.spec.constructors {
+            out.push_str(&display_message(constructor));
+        }
+        out.push_str(&format!("\n{}\n", "Messages".bright_blue().bold()));
+        for message in &self.abi.spec.messages {
+            out.push_str(&display_message(message));
+        }
+        if !self.abi.spec.events.is_empty() {
+            out.push_str(&format!("\n{}\n", "Events".bright_blue().bold()));
+        }
+        for event in &self.abi.spec.events {
+            let args = event
+                .args
+                .iter()
+                .map(|arg| {
+                    let indexed = if arg.indexed { "#[topic] " } else { "" };
+                    format!("{}{}: {}", indexed, arg.name, arg.ty)
+                })
+                .collect::<Vec<_>>();
+            out.push_str(&format!(" {}({})\n", event.name.bold(), args.join(", ")));
+        }
+
+        for extracted in &self.extracted {
+            out.push_str(&format!(
+                "\nExtracted to {}",
+                extracted.display().to_string().bold()
+            ));
+        }
+        out
+    }
+}
+
+/// Renders a single constructor or message with its selector and signature.
+fn display_message(message: &MessageSpec) -> String {
+    let mut flags = Vec::new();
+    if message.mutates {
+        flags.push("mutates");
+    }
+    if message.payable {
+        flags.push("payable");
+    }
+    let flags = if flags.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", flags.join(", "))
+    };
+    format!(
+        " {} {}{}{}\n",
+        message.selector,
+        message.name.bold(),
+        message.signature(),
+        flags
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InspectCommand;
+    use crate::{cmd::contract_abi::tests::flipper_abi, util::tests::with_tmp_dir};
+    use contract_metadata::{
+        CodeHash, Compiler, Contract, ContractMetadata, Language, Source, SourceCompiler,
+        SourceLanguage, SourceWasm,
+    };
+    use semver::Version;
+    use std::fs;
+
+    fn flipper_bundle() -> ContractMetadata {
+        let version = Version::new(3, 0, 0);
+        let source = Source::new(
+            Some(SourceWasm::new(vec![0, 97, 115, 109, 1, 0, 0, 0])),
+            CodeHash([0x2a; 32]),
+            SourceLanguage::new(Language::Ink, version.clone()),
+            SourceCompiler::new(Compiler::RustC, Version::parse("1.56.0-nightly").unwrap()),
+        );
+        let contract = Contract::builder()
+            .name("flipper")
+            .version(version)
+            .authors(vec!["Parity Technologies <admin@parity.io>"])
+            .build()
+            .unwrap();
+        ContractMetadata::new(source, contract, None, flipper_abi())
+    }
+
+    #[test]
+    fn bundle_contents_are_listed_and_extracted() {
+        with_tmp_dir(|path| {
+            // given
+            let bundle = path.join("flipper.contract");
+            fs::write(&bundle, serde_json::to_string(&flipper_bundle())?)?;
+            let cmd = InspectCommand {
+                path: bundle,
+                extract_wasm: Some(path.join("flipper.wasm")),
+                extract_metadata: Some(path.join("metadata.json")),
+            };
+
+            // when
+            let result = cmd.exec()?;
+
+            // then
+            let display = result.display();
+            assert!(display.contains("0x2a2a2a2a"));
+            assert!(display.contains("0x9bae9d5e"));
+            assert!(display.contains("(init_value: bool)"));
+            assert!(display.contains("#[topic] value: bool"));
+            assert_eq!(result.wasm_size, Some(8));
+            assert_eq!(
+                fs::read(path.join("flipper.wasm"))?,
+                vec![0, 97, 115, 109, 1, 0, 0, 0]
+            );
+            let metadata: ContractMetadata =
+                serde_json::from_slice(&fs::read(path.join("metadata.json"))?)?;
+            assert!(metadata.source().wasm().is_none());
+            Ok(())
+        })
+    }
+}
@@ -15,21 +15,42 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use anyhow::{Context, Result};
+use codec::Encode;
 use subxt::{balances::Balances, contracts::*, system::System, ClientBuilder, DefaultNodeRuntime, ContractsTemplateRuntime};
 
 use crate::{ExtrinsicOpts, HexData};
 
+/// The `Contracts::instantiate` call of runtimes which derive the contract address from a salt,
+/// see `cargo contract address`.
+#[derive(Clone, Debug, PartialEq, Encode)]
+pub struct InstantiateWithSaltCall<'a> {
+    #[codec(compact)]
+    pub endowment: <ContractsTemplateRuntime as Balances>::Balance,
+    #[codec(compact)]
+    pub gas_limit: u64,
+    pub code_hash: &'a <ContractsTemplateRuntime as System>::Hash,
+    pub data: &'a [u8],
+    pub salt: &'a [u8],
+}
+
+impl<'a> subxt::Call<ContractsTemplateRuntime> for InstantiateWithSaltCall<'a> {
+    const MODULE: &'static str = "Contracts";
+    const FUNCTION: &'static str = "instantiate";
+}
+
 /// Instantiate a contract stored at the supplied code hash.
 /// Returns the account id of the instantiated contract if successful.
 ///
 /// Creates an extrinsic with the `Contracts::instantiate` Call, submits via RPC, then waits for
-/// the `ContractsEvent::Instantiated` event.
+/// the `ContractsEvent::Instantiated` event. If a `salt` is supplied, it is passed along, which
+/// requires a runtime deriving contract addresses from salts.
 pub(crate) fn execute_instantiate(
     extrinsic_opts: &ExtrinsicOpts,
     endowment: <ContractsTemplateRuntime as Balances>::Balance,
     gas_limit: u64,
     code_hash: <ContractsTemplateRuntime as System>::Hash,
     data: HexData,
+    salt: Option<&[u8]>,
 ) -> Result<<ContractsTemplateRuntime as System>::AccountId> {
     async_std::task::block_on(async move {
         let cli = ClientBuilder::<ContractsTemplateRuntime>::new()
@@ -38,9 +59,22 @@ pub(crate) fn execute_instantiate(
             .await?;
         let signer = extrinsic_opts.signer()?;
 
-        let events = cli
-            .instantiate_and_watch(&signer, endowment, gas_limit, &code_hash, &data.0)
-            .await?;
+        let events = match salt {
+            Some(salt) => {
+                let call = InstantiateWithSaltCall {
+                    endowment,
+                    gas_limit,
+                    code_hash: &code_hash,
+                    data: &data.0,
+                    salt,
+                };
+                cli.watch(call, &signer).await?
+            }
+            None => {
+                cli.instantiate_and_watch(&signer, endowment, gas_limit, &code_hash, &data.0)
+                    .await?
+            }
+        };
         let instantiated = events
             .instantiated()?
             .context("Failed to find Instantiated event")?;
@@ -88,6 +122,7 @@ mod tests {
                 gas_limit,
                 code_hash,
                 HexData::default(),
+                None,
             );
 
             assert_matches!(result, Ok(_));
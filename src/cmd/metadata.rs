@@ -18,14 +18,14 @@ use crate::{
     crate_metadata::CrateMetadata,
     maybe_println, util,
     workspace::{ManifestPath, Workspace},
-    UnstableFlags, Verbosity,
+    BuildMode, OptimizationPasses, UnstableFlags, Verbosity,
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use blake2::digest::{Update as _, VariableOutput as _};
 use colored::Colorize;
 use contract_metadata::{
-    CodeHash, Compiler, Contract, ContractMetadata, Language, Source, SourceCompiler,
+    BuildInfo, CodeHash, Compiler, Contract, ContractMetadata, Language, Source, SourceCompiler,
     SourceLanguage, SourceWasm, User,
 };
 use semver::Version;
@@ -35,7 +35,7 @@ use std::{
 };
 use url::Url;
 
-const METADATA_FILE: &str = "metadata.json";
+pub(crate) const METADATA_FILE: &str = "metadata.json";
 
 /// Metadata generation result.
 #[derive(serde::Serialize)]
@@ -46,6 +46,31 @@ pub struct MetadataResult {
     pub dest_bundle: PathBuf,
 }
 
+/// Location and cargo features of the metadata artifacts to generate.
+pub(crate) struct MetadataArtifacts {
+    /// Directory the metadata and the bundle are written to, also used as cargo target directory.
+    pub target_directory: PathBuf,
+    /// File name of the bundle, without the `.contract` extension.
+    pub artifact_name: String,
+    /// Additional cargo features of the contract to enable, e.g. to select a composable component.
+    pub features: Vec<String>,
+    /// Settings the contract Wasm was built with.
+    pub settings: BuildSettings,
+    /// Environment variables of the cargo invocation, e.g. the code hashes of the components the
+    /// contract depends on.
+    pub env: Vec<(String, String)>,
+}
+
+/// Settings the contract Wasm was built with, recorded in the build info of the metadata.
+#[derive(Clone, Debug)]
+pub(crate) struct BuildSettings {
+    pub build_mode: BuildMode,
+    pub optimization_passes: OptimizationPasses,
+    pub keep_debug_symbols: bool,
+    /// The `RUSTFLAGS` cargo was invoked with.
+    pub rustflags: String,
+}
+
 /// Result of generating the extended contract project metadata
 struct ExtendedMetadataResult {
     source: Source,
@@ -59,16 +84,48 @@ struct ExtendedMetadataResult {
 pub(crate) fn execute(
     crate_metadata: &CrateMetadata,
     final_contract_wasm: &Path,
+    settings: BuildSettings,
     verbosity: Verbosity,
     total_steps: usize,
     unstable_options: &UnstableFlags,
 ) -> Result<MetadataResult> {
-    util::assert_channel()?;
+    let artifacts = MetadataArtifacts {
+        target_directory: crate_metadata.target_directory.clone(),
+        artifact_name: crate_metadata.contract_artifact_name.clone(),
+        features: Vec::new(),
+        settings,
+        env: Vec::new(),
+    };
+    generate(
+        crate_metadata,
+        final_contract_wasm,
+        &artifacts,
+        verbosity,
+        Some(total_steps),
+        unstable_options,
+        None,
+    )
+}
 
-    let target_directory = crate_metadata.target_directory.clone();
+/// Generates the metadata and the bundle described by `artifacts`, see [`execute`].
+///
+/// The progress is only printed if `total_steps` is supplied. If a `log` is supplied, the output
+/// of cargo is captured into it instead of being passed through.
+pub(crate) fn generate(
+    crate_metadata: &CrateMetadata,
+    final_contract_wasm: &Path,
+    artifacts: &MetadataArtifacts,
+    verbosity: Verbosity,
+    total_steps: Option<usize>,
+    unstable_options: &UnstableFlags,
+    log: Option<&mut String>,
+) -> Result<MetadataResult> {
+    util::assert_channel(None)?;
+
+    let target_directory = artifacts.target_directory.clone();
     let out_path_metadata = target_directory.join(METADATA_FILE);
 
-    let fname_bundle = format!("{}.contract", crate_metadata.contract_artifact_name);
+    let fname_bundle = format!("{}.contract", artifacts.artifact_name);
     let out_path_bundle = target_directory.join(fname_bundle);
 
     // build the extended contract project metadata
@@ -76,29 +133,53 @@ pub(crate) fn execute(
         source,
         contract,
         user,
-    } = extended_metadata(crate_metadata, final_contract_wasm)?;
+    } = extended_metadata(
+        crate_metadata,
+        final_contract_wasm,
+        artifacts.settings.clone(),
+    )?;
 
     let generate_metadata = |manifest_path: &ManifestPath| -> Result<()> {
         let mut current_progress = 4;
-        maybe_println!(
-            verbosity,
-            " {} {}",
-            format!("[{}/{}]", current_progress, total_steps).bold(),
-            "Generating metadata".bright_green().bold()
-        );
+        if let Some(total_steps) = total_steps {
+            maybe_println!(
+                verbosity,
+                " {} {}",
+                format!("[{}/{}]", current_progress, total_steps).bold(),
+                "Generating metadata".bright_green().bold()
+            );
+        }
         let target_dir_arg = format!("--target-dir={}", target_directory.to_string_lossy());
-        let stdout = util::invoke_cargo(
-            "run",
-            &[
-                "--package",
-                "metadata-gen",
-                &manifest_path.cargo_arg(),
-                &target_dir_arg,
-                "--release",
-            ],
-            crate_metadata.manifest_path.directory(),
-            verbosity,
-        )?;
+        let mut args = vec![
+            "--package".to_string(),
+            "metadata-gen".to_string(),
+            manifest_path.cargo_arg(),
+            target_dir_arg,
+            "--release".to_string(),
+        ];
+        if !artifacts.features.is_empty() {
+            let features = artifacts
+                .features
+                .iter()
+                .map(|feature| format!("contract/{}", feature))
+                .collect::<Vec<_>>();
+            args.push(format!("--features={}", features.join(",")));
+        }
+        let working_dir = crate_metadata.manifest_path.directory();
+        let stdout = match log {
+            Some(log) => {
+                let output = util::invoke_cargo_captured(
+                    "run",
+                    &args,
+                    working_dir,
+                    verbosity,
+                    &artifacts.env,
+                )?;
+                log.push_str(&output.stderr);
+                output.stdout
+            }
+            None => util::invoke_cargo("run", &args, working_dir, verbosity)?,
+        };
 
         let ink_meta: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&stdout)?;
         let metadata = ContractMetadata::new(source, contract, user, ink_meta);
@@ -110,12 +191,14 @@ pub(crate) fn execute(
             current_progress += 1;
         }
 
-        maybe_println!(
-            verbosity,
-            " {} {}",
-            format!("[{}/{}]", current_progress, total_steps).bold(),
-            "Generating bundle".bright_green().bold()
-        );
+        if let Some(total_steps) = total_steps {
+            maybe_println!(
+                verbosity,
+                " {} {}",
+                format!("[{}/{}]", current_progress, total_steps).bold(),
+                "Generating bundle".bright_green().bold()
+            );
+        }
         let contents = serde_json::to_string(&metadata)?;
         fs::write(&out_path_bundle, contents)?;
 
@@ -146,10 +229,9 @@ pub(crate) fn execute(
 fn extended_metadata(
     crate_metadata: &CrateMetadata,
     final_contract_wasm: &Path,
+    settings: BuildSettings,
 ) -> Result<ExtendedMetadataResult> {
     let contract_package = &crate_metadata.root_package;
-    let ink_version = &crate_metadata.ink_version;
-    let rust_version = Version::parse(&rustc_version::version()?.to_string())?;
     let contract_name = contract_package.name.clone();
     let contract_version = Version::parse(&contract_package.version.to_string())?;
     let contract_authors = contract_package.authors.clone();
@@ -163,13 +245,7 @@ fn extended_metadata(
         .transpose()?;
     let homepage = crate_metadata.homepage.clone();
     let license = contract_package.license.clone();
-    let source = {
-        let lang = SourceLanguage::new(Language::Ink, ink_version.clone());
-        let compiler = SourceCompiler::new(Compiler::RustC, rust_version);
-        let wasm = fs::read(final_contract_wasm)?;
-        let hash = blake2_hash(wasm.as_slice());
-        Source::new(Some(SourceWasm::new(wasm)), hash, lang, compiler)
-    };
+    let source = contract_source(crate_metadata, final_contract_wasm, settings, None)?;
 
     // Required contract fields
     let mut builder = Contract::builder();
@@ -212,8 +288,55 @@ fn extended_metadata(
     })
 }
 
+/// Describes the source of the final contract Wasm, i.e. its code hash, the language it is
+/// written in, the compiler of the rustup `toolchain` and the settings it was built with.
+pub(crate) fn contract_source(
+    crate_metadata: &CrateMetadata,
+    final_contract_wasm: &Path,
+    settings: BuildSettings,
+    toolchain: Option<&str>,
+) -> Result<Source> {
+    let ink_version = &crate_metadata.ink_version;
+    let rust_version = Version::parse(&util::rustc_version_meta(toolchain)?.semver.to_string())?;
+    let lang = SourceLanguage::new(Language::Ink, ink_version.clone());
+    let compiler = SourceCompiler::new(Compiler::RustC, rust_version);
+    let wasm = fs::read(final_contract_wasm)?;
+    let hash = blake2_hash(wasm.as_slice());
+    let source = Source::new(Some(SourceWasm::new(wasm)), hash, lang, compiler);
+    Ok(source.with_build_info(build_info(crate_metadata, settings, toolchain)?))
+}
+
+/// Collects the provenance of the contract build.
+///
+/// Without an explicit `toolchain`, the toolchain the rustup proxy selected for this process is
+/// recorded, if any.
+fn build_info(
+    crate_metadata: &CrateMetadata,
+    settings: BuildSettings,
+    toolchain: Option<&str>,
+) -> Result<BuildInfo> {
+    let cargo_lock = crate_metadata.cargo_meta.workspace_root.join("Cargo.lock");
+    let cargo_lock_hash = fs::read(&cargo_lock)
+        .ok()
+        .map(|cargo_lock| blake2_hash(&cargo_lock));
+    let source_dir = crate_metadata.manifest_path.absolute_directory()?;
+    Ok(BuildInfo {
+        cargo_contract_version: env!("CARGO_CONTRACT_CLI_IMPL_VERSION").to_string(),
+        wasm_opt_version: util::wasm_opt_version(),
+        optimization_passes: settings.optimization_passes.to_string(),
+        build_mode: settings.build_mode.to_string(),
+        keep_debug_symbols: settings.keep_debug_symbols,
+        rustflags: settings.rustflags,
+        rust_toolchain: toolchain
+            .map(str::to_string)
+            .or_else(|| std::env::var("RUSTUP_TOOLCHAIN").ok()),
+        cargo_lock_hash,
+        git_commit: util::git_commit(&source_dir),
+    })
+}
+
 /// Returns the blake2 hash of the submitted slice.
-fn blake2_hash(code: &[u8]) -> CodeHash {
+pub(crate) fn blake2_hash(code: &[u8]) -> CodeHash {
     let mut output = [0u8; 32];
     let mut blake2 = blake2::VarBlake2b::new_keyed(&[], 32);
     blake2.update(code);
@@ -221,6 +344,21 @@ fn blake2_hash(code: &[u8]) -> CodeHash {
     CodeHash(output)
 }
 
+/// Parses a hex encoded code hash, with or without a `0x` prefix.
+pub(crate) fn parse_code_hash(input: &str) -> Result<CodeHash> {
+    let bytes = hex::decode(input.trim_start_matches("0x"))
+        .context(format!("Invalid code hash '{}'", input))?;
+    if bytes.len() != 32 {
+        anyhow::bail!(
+            "Invalid code hash '{}', expected 32 hex encoded bytes",
+            input
+        );
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&bytes);
+    Ok(CodeHash(hash))
+}
+
 #[cfg(feature = "test-ci-only")]
 #[cfg(test)]
 mod tests {
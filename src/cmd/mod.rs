@@ -14,24 +14,41 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+pub mod address;
 pub mod build;
 #[cfg(feature = "extrinsics")]
 mod call;
 pub mod composable_build;
+mod composable_cache;
+#[cfg(feature = "extrinsics")]
+pub mod composable_deploy;
+#[cfg(feature = "extrinsics")]
+pub mod composable_exec;
+pub mod composable_validate;
+mod composable_wat;
+pub(crate) mod contract_abi;
 #[cfg(feature = "extrinsics")]
 pub mod deploy;
 #[cfg(feature = "extrinsics")]
+pub mod dry_run;
+pub mod inspect;
+#[cfg(feature = "extrinsics")]
 mod instantiate;
 pub mod metadata;
 pub mod new;
 pub mod test;
+#[cfg(feature = "extrinsics")]
+mod transcode;
+pub mod verify;
 
 pub(crate) use self::{
     build::{BuildCommand, CheckCommand},
+    inspect::InspectCommand,
     test::TestCommand,
+    verify::VerifyCommand,
 };
 #[cfg(feature = "extrinsics")]
 pub(crate) use self::{
-    call::call_regular_contract, call::execute_call, call::execute_contract_call,
+    call::call_regular_contract, call::execute_call, call::execute_contract_call, call::CallResult,
     deploy::execute_deploy, instantiate::execute_instantiate,
 };
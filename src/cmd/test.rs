@@ -56,7 +56,7 @@ impl TestResult {
 
 /// Executes `cargo +nightly test`.
 pub(crate) fn execute(manifest_path: &ManifestPath, verbosity: Verbosity) -> Result<TestResult> {
-    util::assert_channel()?;
+    util::assert_channel(None)?;
 
     maybe_println!(
         verbosity,
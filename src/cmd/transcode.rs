@@ -0,0 +1,525 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! SCALE encoding of human-readable contract arguments and decoding of contract output, driven
+//! by the type registry of the contract metadata.
+//!
+//! Primitive arguments are supplied as is, e.g. `42`, `true` or `hello`. Arguments of other
+//! types are supplied as JSON:
+//!
+//! - sequences, arrays and tuples as JSON arrays, byte arrays also as `0x` prefixed hex strings
+//! - structs as JSON objects keyed by field name, or as JSON arrays of their fields
+//! - enum variants without fields by name, e.g. `"Ready"`, and variants with fields as objects,
+//!   e.g. `{"Some": 42}`. `null` encodes `None`
+//!
+//! Types wrapping a single field, e.g. `AccountId` or `Balance`, are supplied like their field.
+//!
+//! Decoded values are rendered in the same notation, so they can be passed back as arguments.
+
+use anyhow::{Context, Result};
+use codec::{Compact, Decode, Encode};
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+
+/// The type registry of the contract metadata.
+#[derive(Debug, Clone, Default)]
+pub struct TypeRegistry {
+    types: Vec<Value>,
+}
+
+impl TypeRegistry {
+    /// Reads the registry from the `types` section of the ABI.
+    pub fn new(types: Vec<Value>) -> Self {
+        TypeRegistry { types }
+    }
+
+    /// Returns the definition of the type with the supplied id.
+    ///
+    /// Registries which list their ids, like those of `scale-info` 1.0, are looked up by id. Older
+    /// registries are indexed implicitly, starting at 1.
+    fn definition(&self, id: u32) -> Result<&Map<String, Value>> {
+        let entry = self
+            .types
+            .iter()
+            .find(|ty| ty.get("id").and_then(Value::as_u64) == Some(u64::from(id)))
+            .and_then(|ty| ty.get("type"))
+            .or_else(|| self.types.get((id as usize).checked_sub(1)?));
+        entry
+            .and_then(|ty| ty.get("def"))
+            .and_then(Value::as_object)
+            .context(format!("Type {} is missing from the type registry", id))
+    }
+
+    /// Encodes a human-readable argument of the type with the supplied id.
+    pub fn encode_arg(&self, id: u32, arg: &str) -> Result<Vec<u8>> {
+        let value = match (
+            self.definition(id)?.get("primitive"),
+            serde_json::from_str(arg),
+        ) {
+            (Some(Value::String(primitive)), _) if primitive == "str" || primitive == "char" => {
+                Value::String(arg.to_string())
+            }
+            (_, Ok(value)) => value,
+            (_, Err(_)) => Value::String(arg.to_string()),
+        };
+        let mut out = Vec::new();
+        self.encode_value(id, &value, &mut out)?;
+        Ok(out)
+    }
+
+    fn encode_value(&self, id: u32, value: &Value, out: &mut Vec<u8>) -> Result<()> {
+        let def = self.definition(id)?;
+        let (kind, def) = def
+            .iter()
+            .next()
+            .context(format!("Type {} has no definition", id))?;
+        match kind.as_str() {
+            "primitive" => encode_primitive(def.as_str().unwrap_or_default(), value, out),
+            "compact" => {
+                let int = parse_int::<u128>(value)?;
+                Compact(int).encode_to(out);
+                Ok(())
+            }
+            "array" | "sequence" => {
+                let elem = type_id(&def["type"])?;
+                let len = def.get("len").and_then(Value::as_u64);
+                let elems = match value {
+                    Value::String(hex) if self.is_byte(elem) => hex_bytes(hex)?
+                        .into_iter()
+                        .map(Value::from)
+                        .collect::<Vec<_>>(),
+                    Value::Array(elems) => elems.clone(),
+                    _ => anyhow::bail!("Expected an array, got {}", value),
+                };
+                match len {
+                    Some(len) if elems.len() as u64 != len => {
+                        anyhow::bail!("Expected {} elements, got {}", len, elems.len())
+                    }
+                    Some(_) => (),
+                    None => Compact(elems.len() as u32).encode_to(out),
+                }
+                for elem_value in &elems {
+                    self.encode_value(elem, elem_value, out)?;
+                }
+                Ok(())
+            }
+            "tuple" => {
+                let fields = def.as_array().cloned().unwrap_or_default();
+                let values = match value {
+                    Value::Array(values) => values.clone(),
+                    Value::Null if fields.is_empty() => Vec::new(),
+                    _ if fields.len() == 1 => vec![value.clone()],
+                    _ => anyhow::bail!(
+                        "Expected a tuple of {} elements, got {}",
+                        fields.len(),
+                        value
+                    ),
+                };
+                if values.len() != fields.len() {
+                    anyhow::bail!(
+                        "Expected a tuple of {} elements, got {}",
+                        fields.len(),
+                        value
+                    );
+                }
+                for (field, field_value) in fields.iter().zip(&values) {
+                    self.encode_value(type_id(field)?, field_value, out)?;
+                }
+                Ok(())
+            }
+            "composite" => self.encode_fields(def.get("fields"), value, out),
+            "variant" => {
+                let variants = def
+                    .get("variants")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                let (name, fields_value) = match value {
+                    Value::Null => ("None", Value::Null),
+                    Value::String(name) => (name.as_str(), Value::Null),
+                    Value::Object(object) if object.len() == 1 => {
+                        let (name, fields_value) = object.iter().next().expect("one entry");
+                        (name.as_str(), fields_value.clone())
+                    }
+                    _ => anyhow::bail!("Expected an enum variant, got {}", value),
+                };
+                let (index, variant) = variants
+                    .iter()
+                    .enumerate()
+                    .find(|(_, variant)| variant["name"] == name)
+                    .with_context(|| {
+                        let names = variants
+                            .iter()
+                            .filter_map(|variant| variant["name"].as_str())
+                            .collect::<Vec<_>>();
+                        format!(
+                            "Unknown variant '{}', expected one of: {}",
+                            name,
+                            names.join(", ")
+                        )
+                    })?;
+                let index = variant
+                    .get("discriminant")
+                    .or_else(|| variant.get("index"))
+                    .and_then(Value::as_u64)
+                    .unwrap_or(index as u64);
+                out.push(index as u8);
+                self.encode_fields(variant.get("fields"), &fields_value, out)
+            }
+            _ => anyhow::bail!("Encoding of {} types is not supported", kind),
+        }
+    }
+
+    /// Encodes the fields of a struct or an enum variant.
+    fn encode_fields(
+        &self,
+        fields: Option<&Value>,
+        value: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let fields = fields
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        match (fields.as_slice(), value) {
+            ([], Value::Null) => Ok(()),
+            ([], _) => anyhow::bail!("Expected no fields, got {}", value),
+            ([field], _) => {
+                // Types wrapping a single field are supplied like their field.
+                let field_value = match (field_name(field), value) {
+                    (Some(name), Value::Object(object)) => object.get(name).unwrap_or(value),
+                    _ => value,
+                };
+                self.encode_value(type_id(&field["type"])?, field_value, out)
+            }
+            (_, Value::Array(values)) if values.len() == fields.len() => {
+                for (field, field_value) in fields.iter().zip(values) {
+                    self.encode_value(type_id(&field["type"])?, field_value, out)?;
+                }
+                Ok(())
+            }
+            (_, Value::Object(object)) => {
+                for field in &fields {
+                    let name = field_name(field).context("Expected the fields as an array")?;
+                    let field_value = object
+                        .get(name)
+                        .context(format!("Missing field '{}'", name))?;
+                    self.encode_value(type_id(&field["type"])?, field_value, out)
+                        .context(format!("Encoding field '{}'", name))?;
+                }
+                Ok(())
+            }
+            _ => anyhow::bail!("Expected {} fields, got {}", fields.len(), value),
+        }
+    }
+
+    /// Returns `true` if the type with the supplied id is `u8`.
+    fn is_byte(&self, id: u32) -> bool {
+        self.definition(id)
+            .map(|def| def.get("primitive") == Some(&Value::from("u8")))
+            .unwrap_or(false)
+    }
+
+    /// Decodes a value of the type with the supplied id from the front of `input`.
+    pub fn decode_value(&self, id: u32, input: &mut &[u8]) -> Result<Value> {
+        let def = self.definition(id)?;
+        let (kind, def) = def
+            .iter()
+            .next()
+            .context(format!("Type {} has no definition", id))?;
+        match kind.as_str() {
+            "primitive" => decode_primitive(def.as_str().unwrap_or_default(), input),
+            "compact" => Ok(int_value(Compact::<u128>::decode(input)?.0)),
+            "array" | "sequence" => {
+                let elem = type_id(&def["type"])?;
+                let len = match def.get("len").and_then(Value::as_u64) {
+                    Some(len) => len as usize,
+                    None => Compact::<u32>::decode(input)?.0 as usize,
+                };
+                if self.is_byte(elem) {
+                    if input.len() < len {
+                        anyhow::bail!("Expected {} bytes, got {}", len, input.len());
+                    }
+                    let (bytes, rest) = input.split_at(len);
+                    *input = rest;
+                    return Ok(Value::String(format!("0x{}", hex::encode(bytes))));
+                }
+                (0..len)
+                    .map(|_| self.decode_value(elem, input))
+                    .collect::<Result<_>>()
+                    .map(Value::Array)
+            }
+            "tuple" => {
+                let fields = def.as_array().cloned().unwrap_or_default();
+                if fields.is_empty() {
+                    return Ok(Value::Null);
+                }
+                fields
+                    .iter()
+                    .map(|field| self.decode_value(type_id(field)?, input))
+                    .collect::<Result<_>>()
+                    .map(Value::Array)
+            }
+            "composite" => self.decode_fields(def.get("fields"), input),
+            "variant" => {
+                let variants = def
+                    .get("variants")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                let index = u8::decode(input)?;
+                let variant = variants
+                    .iter()
+                    .enumerate()
+                    .find(|(position, variant)| {
+                        variant
+                            .get("discriminant")
+                            .or_else(|| variant.get("index"))
+                            .and_then(Value::as_u64)
+                            .unwrap_or(*position as u64)
+                            == u64::from(index)
+                    })
+                    .map(|(_, variant)| variant)
+                    .context(format!("Invalid variant index {} of type {}", index, id))?;
+                let name = variant["name"].as_str().unwrap_or_default().to_string();
+                let fields = self.decode_fields(variant.get("fields"), input)?;
+                if fields.is_null() {
+                    Ok(Value::String(name))
+                } else {
+                    let mut object = Map::new();
+                    object.insert(name, fields);
+                    Ok(Value::Object(object))
+                }
+            }
+            _ => anyhow::bail!("Decoding of {} types is not supported", kind),
+        }
+    }
+
+    /// Decodes the fields of a struct or an enum variant.
+    ///
+    /// Named fields are decoded into an object, unnamed fields into an array. A single unnamed
+    /// field is decoded as is.
+    fn decode_fields(&self, fields: Option<&Value>, input: &mut &[u8]) -> Result<Value> {
+        let fields = fields
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        match fields.as_slice() {
+            [] => Ok(Value::Null),
+            [field] if field_name(field).is_none() => {
+                self.decode_value(type_id(&field["type"])?, input)
+            }
+            _ if fields.iter().all(|field| field_name(field).is_some()) => {
+                let mut object = Map::new();
+                for field in &fields {
+                    let name = field_name(field).expect("all fields are named");
+                    let value = self
+                        .decode_value(type_id(&field["type"])?, input)
+                        .context(format!("Decoding field '{}'", name))?;
+                    object.insert(name.to_string(), value);
+                }
+                Ok(Value::Object(object))
+            }
+            _ => fields
+                .iter()
+                .map(|field| self.decode_value(type_id(&field["type"])?, input))
+                .collect::<Result<_>>()
+                .map(Value::Array),
+        }
+    }
+}
+
+/// Reads a type reference, either a plain id or an object of the form `{"type": id}`.
+fn type_id(value: &Value) -> Result<u32> {
+    value
+        .as_u64()
+        .or_else(|| value.get("type").and_then(Value::as_u64))
+        .map(|id| id as u32)
+        .context(format!("Invalid type reference {}", value))
+}
+
+fn field_name(field: &Value) -> Option<&str> {
+    field.get("name").and_then(Value::as_str)
+}
+
+fn encode_primitive(primitive: &str, value: &Value, out: &mut Vec<u8>) -> Result<()> {
+    match primitive {
+        "bool" => match value {
+            Value::Bool(b) => b.encode_to(out),
+            _ => anyhow::bail!("Expected true or false, got {}", value),
+        },
+        "char" | "str" => match value {
+            Value::String(s) if primitive == "str" => s.encode_to(out),
+            Value::String(s) if s.chars().count() == 1 => {
+                (s.chars().next().expect("one char") as u32).encode_to(out)
+            }
+            _ => anyhow::bail!("Expected a {}, got {}", primitive, value),
+        },
+        "u8" => parse_int::<u8>(value)?.encode_to(out),
+        "u16" => parse_int::<u16>(value)?.encode_to(out),
+        "u32" => parse_int::<u32>(value)?.encode_to(out),
+        "u64" => parse_int::<u64>(value)?.encode_to(out),
+        "u128" => parse_int::<u128>(value)?.encode_to(out),
+        "i8" => parse_int::<i8>(value)?.encode_to(out),
+        "i16" => parse_int::<i16>(value)?.encode_to(out),
+        "i32" => parse_int::<i32>(value)?.encode_to(out),
+        "i64" => parse_int::<i64>(value)?.encode_to(out),
+        "i128" => parse_int::<i128>(value)?.encode_to(out),
+        _ => anyhow::bail!("Encoding of {} is not supported", primitive),
+    }
+    Ok(())
+}
+
+fn decode_primitive(primitive: &str, input: &mut &[u8]) -> Result<Value> {
+    let value = match primitive {
+        "bool" => Value::Bool(bool::decode(input)?),
+        "char" => {
+            let char = u32::decode(input)?;
+            let char = std::char::from_u32(char).context(format!("Invalid char {}", char))?;
+            Value::String(char.to_string())
+        }
+        "str" => Value::String(String::decode(input)?),
+        "u8" => Value::from(u8::decode(input)?),
+        "u16" => Value::from(u16::decode(input)?),
+        "u32" => Value::from(u32::decode(input)?),
+        "u64" => Value::from(u64::decode(input)?),
+        "u128" => int_value(u128::decode(input)?),
+        "i8" => Value::from(i8::decode(input)?),
+        "i16" => Value::from(i16::decode(input)?),
+        "i32" => Value::from(i32::decode(input)?),
+        "i64" => Value::from(i64::decode(input)?),
+        "i128" => {
+            let int = i128::decode(input)?;
+            match i64::try_from(int) {
+                Ok(int) => Value::from(int),
+                Err(_) => Value::String(int.to_string()),
+            }
+        }
+        _ => anyhow::bail!("Decoding of {} is not supported", primitive),
+    };
+    Ok(value)
+}
+
+/// Renders an integer as JSON number, or as string if it does not fit into 64 bits.
+fn int_value(int: u128) -> Value {
+    match u64::try_from(int) {
+        Ok(int) => Value::from(int),
+        Err(_) => Value::String(int.to_string()),
+    }
+}
+
+/// Parses an integer from a JSON number or a string, the latter allows for 128 bit integers.
+fn parse_int<T>(value: &Value) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let int = match value {
+        Value::Number(number) => number.to_string(),
+        Value::String(string) => string.replace('_', ""),
+        _ => anyhow::bail!("Expected an integer, got {}", value),
+    };
+    int.parse::<T>()
+        .context(format!("Invalid {} '{}'", std::any::type_name::<T>(), int))
+}
+
+/// Decodes a hex string, with or without `0x` prefix.
+pub(crate) fn hex_bytes(input: &str) -> Result<Vec<u8>> {
+    hex::decode(input.trim_start_matches("0x")).context(format!("Invalid hex string '{}'", input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TypeRegistry;
+    use codec::Encode;
+    use serde_json::json;
+
+    fn registry() -> TypeRegistry {
+        let types = json!([
+            { "def": { "primitive": "u128" } },
+            { "def": { "primitive": "u8" } },
+            { "def": { "array": { "len": 4, "type": 2 } } },
+            { "def": { "composite": { "fields": [{ "type": 3, "typeName": "[u8; 4]" }] } }, "path": ["Id"] },
+            { "def": { "primitive": "str" } },
+            { "def": { "sequence": { "type": 1 } } },
+            { "def": { "variant": { "variants": [{ "name": "None" }, { "name": "Some", "fields": [{ "type": 1 }] }] } } },
+            { "def": { "composite": { "fields": [{ "name": "id", "type": 4 }, { "name": "amount", "type": 1 }] } } }
+        ]);
+        TypeRegistry::new(types.as_array().unwrap().clone())
+    }
+
+    #[test]
+    fn primitives_are_encoded_from_plain_strings() {
+        let registry = registry();
+
+        assert_eq!(registry.encode_arg(1, "1_000").unwrap(), 1_000u128.encode());
+        assert_eq!(registry.encode_arg(5, "hello").unwrap(), "hello".encode());
+        assert_eq!(registry.encode_arg(5, "42").unwrap(), "42".encode());
+        assert!(registry.encode_arg(2, "256").is_err());
+    }
+
+    #[test]
+    fn structured_arguments_are_encoded_from_json() {
+        let registry = registry();
+
+        assert_eq!(
+            registry.encode_arg(4, "0x01020304").unwrap(),
+            vec![1, 2, 3, 4]
+        );
+        assert!(registry.encode_arg(4, "0x0102").is_err());
+        assert_eq!(
+            registry.encode_arg(6, "[1, 2]").unwrap(),
+            vec![1u128, 2].encode()
+        );
+        assert_eq!(registry.encode_arg(7, "null").unwrap(), vec![0]);
+        assert_eq!(
+            registry.encode_arg(7, r#"{"Some": 5}"#).unwrap(),
+            Some(5u128).encode()
+        );
+        assert_eq!(
+            registry
+                .encode_arg(8, r#"{"id": "0x01020304", "amount": 7}"#)
+                .unwrap(),
+            ([1u8, 2, 3, 4], 7u128).encode()
+        );
+        let err = registry.encode_arg(7, r#""Maybe""#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown variant 'Maybe', expected one of: None, Some"
+        );
+    }
+
+    #[test]
+    fn decoded_values_round_trip() {
+        let registry = registry();
+        let values = vec![
+            (1, json!("340282366920938463463374607431768211455")),
+            (4, json!("0x01020304")),
+            (6, json!([1, 2])),
+            (7, json!("None")),
+            (7, json!({ "Some": 5 })),
+            (8, json!({ "id": "0x01020304", "amount": 7 })),
+        ];
+
+        for (id, value) in values {
+            let encoded = registry.encode_arg(id, &value.to_string()).unwrap();
+            let mut input = &encoded[..];
+
+            assert_eq!(registry.decode_value(id, &mut input).unwrap(), value);
+            assert!(input.is_empty());
+        }
+    }
+}
@@ -0,0 +1,570 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    build::{self, ExecuteArgs},
+    metadata::{self, BuildSettings},
+};
+use crate::{
+    crate_metadata::CrateMetadata,
+    util,
+    workspace::{Manifest, ManifestPath},
+    BuildArtifacts, BuildMode, OptimizationPasses, UnstableFlags, UnstableOptions, VerbosityFlags,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use rustc_version::Channel;
+use semver::Version;
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    fs,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+/// Keys of the `source` section which are outputs rather than inputs of the build.
+const SOURCE_OUTPUTS: [&str; 2] = ["hash", "wasm"];
+
+/// Rebuilds the contract from source and checks that it results in the expected code hash.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "verify")]
+pub struct VerifyCommand {
+    /// Path to the Cargo.toml of the contract to verify
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Path to the `<name>.contract` bundle or `metadata.json` the build inputs and the code hash
+    /// are taken from
+    #[structopt(long, parse(from_os_str), required_unless = "code-hash")]
+    bundle: Option<PathBuf>,
+    /// The expected code hash, e.g. as deployed on chain. Takes precedence over the hash
+    /// recorded in the bundle
+    #[structopt(long)]
+    code_hash: Option<String>,
+    /// Rustup toolchain to rebuild the contract with, e.g. `nightly-2021-09-01`. Defaults to the
+    /// nightly toolchain recorded in the bundle, unless the default toolchain has the recorded
+    /// compiler version
+    #[structopt(long)]
+    toolchain: Option<String>,
+    /// Rebuild the contract without debug functionality, see `cargo contract build --help`.
+    /// Defaults to the build mode recorded in the bundle
+    #[structopt(long = "--release")]
+    build_release: bool,
+    /// Number of optimization passes passed to wasm-opt, see `cargo contract build --help`.
+    /// Defaults to the passes recorded in the bundle
+    #[structopt(long)]
+    optimization_passes: Option<OptimizationPasses>,
+    /// Do not remove symbols (Wasm name section) when optimizing. Defaults to the setting
+    /// recorded in the bundle
+    #[structopt(long)]
+    keep_debug_symbols: bool,
+    #[structopt(flatten)]
+    verbosity: VerbosityFlags,
+    #[structopt(flatten)]
+    unstable_options: UnstableOptions,
+}
+
+impl VerifyCommand {
+    pub fn exec(&self) -> Result<VerificationResult> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let unstable_flags: UnstableFlags =
+            TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?;
+        let verbosity = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
+
+        let recorded_source = match &self.bundle {
+            Some(bundle) => Some(read_recorded_source(bundle)?),
+            None => None,
+        };
+        let expected_hash = match (&self.code_hash, &recorded_source) {
+            (Some(code_hash), _) => parse_code_hash(code_hash)?,
+            (None, Some(source)) => source
+                .get("hash")
+                .and_then(Value::as_str)
+                .map(parse_code_hash)
+                .transpose()?
+                .context("The bundle does not record a code hash, pass --code-hash")?,
+            (None, None) => anyhow::bail!("Either --bundle or --code-hash must be supplied"),
+        };
+
+        // Settings passed on the command line take precedence over those recorded in the bundle.
+        let recorded = |key: &str| {
+            recorded_source
+                .as_ref()
+                .and_then(|source| source.get("build_info"))
+                .and_then(|build_info| build_info.get(key))
+        };
+        let recorded_str = |key: &str| recorded(key).and_then(Value::as_str);
+        let toolchain = match (&self.toolchain, &recorded_source) {
+            (Some(toolchain), _) => Some(toolchain.clone()),
+            (None, Some(source)) => match source.get("compiler").and_then(Value::as_str) {
+                Some(compiler) => {
+                    let default = util::rustc_version_meta(None)?.semver;
+                    recorded_toolchain(compiler, recorded_str("rust_toolchain"), &default)?
+                }
+                None => None,
+            },
+            (None, None) => None,
+        };
+        assert_wasm_opt_version(
+            recorded_str("wasm_opt_version"),
+            util::wasm_opt_version().as_deref(),
+        )?;
+        let optimization_passes = match self.optimization_passes {
+            Some(opt_passes) => opt_passes,
+            None => match recorded_str("optimization_passes") {
+                Some(opt_passes) => opt_passes.parse()?,
+                None => Manifest::new(manifest_path.clone())?
+                    .get_profile_optimization_passes()
+                    .unwrap_or_default(),
+            },
+        };
+        let build_mode = match (self.build_release, recorded_str("build_mode")) {
+            (true, _) => BuildMode::Release,
+            (false, Some(build_mode)) => build_mode.parse()?,
+            (false, None) => BuildMode::Debug,
+        };
+        let keep_debug_symbols = self.keep_debug_symbols
+            || recorded("keep_debug_symbols")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+        let args = ExecuteArgs {
+            manifest_path,
+            verbosity,
+            build_mode,
+            build_artifact: BuildArtifacts::CodeOnly,
+            unstable_flags,
+            optimization_passes,
+            keep_debug_symbols,
+            toolchain,
+            rustflags: recorded_str("rustflags").map(str::to_string),
+            ..Default::default()
+        };
+
+        execute(args, expected_hash, recorded_source.as_ref())
+    }
+}
+
+/// A build input whose recorded value differs from the one of the rebuild.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct BuildInputDifference {
+    /// Path of the input in the `source` section of the metadata, e.g. `compiler`.
+    pub input: String,
+    /// The value recorded in the bundle, if any.
+    pub recorded: Option<String>,
+    /// The value of the rebuild, if any.
+    pub local: Option<String>,
+}
+
+/// Result of rebuilding a contract for verification.
+#[derive(serde::Serialize)]
+pub struct VerificationResult {
+    /// The expected code hash, hex encoded.
+    pub expected_hash: String,
+    /// The code hash of the rebuilt contract, hex encoded.
+    pub actual_hash: String,
+    /// Path to the rebuilt Wasm file.
+    pub dest_wasm: PathBuf,
+    /// Build inputs which differ between the bundle and the rebuild.
+    pub differences: Vec<BuildInputDifference>,
+}
+
+impl VerificationResult {
+    /// Returns `true` if the rebuilt contract has the expected code hash.
+    pub fn is_match(&self) -> bool {
+        self.expected_hash == self.actual_hash
+    }
+
+    pub fn display(&self) -> String {
+        let mut out = if self.is_match() {
+            format!(
+                "\n{} {}",
+                "The rebuilt contract matches the code hash".green().bold(),
+                self.expected_hash.bold()
+            )
+        } else {
+            format!(
+                "\n{}\n  expected: {}\n  actual:   {}",
+                "The rebuilt contract does not match the code hash"
+                    .bright_red()
+                    .bold(),
+                self.expected_hash,
+                self.actual_hash
+            )
+        };
+        out.push_str(&format!(
+            "\n\nThe rebuilt contract can be found at:\n{}",
+            self.dest_wasm.display().to_string().bold()
+        ));
+        if !self.differences.is_empty() {
+            out.push_str(&format!(
+                "\n\n{}",
+                "The following build inputs differ from the bundle:".bold()
+            ));
+            for difference in &self.differences {
+                out.push_str(&format!(
+                    "\n  {}: recorded {}, rebuilt with {}",
+                    difference.input,
+                    difference.recorded.as_deref().unwrap_or("nothing"),
+                    difference.local.as_deref().unwrap_or("nothing")
+                ));
+            }
+        } else if !self.is_match() {
+            out.push_str(
+                "\n\nNo recorded build input differs, the contract sources or build flags may \
+                have changed.",
+            );
+        }
+        out
+    }
+}
+
+/// Rebuilds the contract and compares its code hash to `expected_hash`.
+///
+/// If the `recorded_source` section of a bundle is supplied, all of its build inputs are compared
+/// to those of the rebuild.
+pub(crate) fn execute(
+    args: ExecuteArgs,
+    expected_hash: String,
+    recorded_source: Option<&Map<String, Value>>,
+) -> Result<VerificationResult> {
+    let crate_metadata = CrateMetadata::collect(&args.manifest_path)?;
+    let toolchain = args.toolchain.clone();
+    let settings = BuildSettings {
+        build_mode: args.build_mode,
+        optimization_passes: args.optimization_passes,
+        keep_debug_symbols: args.keep_debug_symbols,
+        rustflags: args
+            .rustflags
+            .clone()
+            .unwrap_or_else(|| build::WASM_TARGET_RUSTFLAGS.to_string()),
+    };
+    let build_result = build::execute(args)?;
+    let dest_wasm = build_result
+        .dest_wasm
+        .expect("the Wasm is built for the code-only artifact; qed");
+
+    let source =
+        metadata::contract_source(&crate_metadata, &dest_wasm, settings, toolchain.as_deref())?;
+    let local_source = match serde_json::to_value(&source)? {
+        Value::Object(local_source) => local_source,
+        _ => unreachable!("the source section is serialized as a map; qed"),
+    };
+    let actual_hash = local_source
+        .get("hash")
+        .and_then(Value::as_str)
+        .expect("the source section records the code hash; qed")
+        .to_string();
+    let differences = match recorded_source {
+        Some(recorded_source) => build_input_differences(recorded_source, &local_source),
+        None => Vec::new(),
+    };
+    Ok(VerificationResult {
+        expected_hash,
+        actual_hash,
+        dest_wasm,
+        differences,
+    })
+}
+
+/// Reads the `source` section of a `<name>.contract` bundle or `metadata.json` file.
+fn read_recorded_source(bundle: &Path) -> Result<Map<String, Value>> {
+    let contents = fs::read(bundle).context(format!("Reading bundle from {}", bundle.display()))?;
+    let mut metadata: Value = serde_json::from_slice(&contents)
+        .context(format!("Parsing bundle {}", bundle.display()))?;
+    match metadata.get_mut("source").map(Value::take) {
+        Some(Value::Object(source)) => Ok(source),
+        _ => anyhow::bail!("The bundle {} has no source section", bundle.display()),
+    }
+}
+
+/// Returns the rustup toolchain to rebuild a bundle with, whose `compiler` and rustup `toolchain`
+/// are recorded, e.g. `rustc 1.56.0-nightly` and `nightly-2021-09-01-x86_64-unknown-linux-gnu`.
+///
+/// Returns `None` if the `default` toolchain has the recorded compiler version. Otherwise the
+/// dated nightly toolchain is derived from the recorded toolchain. Fails if the bundle was built
+/// on a channel contracts cannot be built with, or if the recorded toolchain is not dated.
+fn recorded_toolchain(
+    compiler: &str,
+    toolchain: Option<&str>,
+    default: &Version,
+) -> Result<Option<String>> {
+    let version = compiler
+        .strip_prefix("rustc ")
+        .and_then(|version| Version::parse(version).ok())
+        .context(format!(
+            "The bundle records the unknown compiler '{}', pass --toolchain",
+            compiler
+        ))?;
+    util::assert_nightly(channel_of(&version))
+        .context(format!("The bundle was built with {}", compiler))?;
+    if &version == default {
+        return Ok(None);
+    }
+    match toolchain {
+        Some(toolchain) => match dated_nightly(toolchain) {
+            Some(dated) => Ok(Some(dated)),
+            None => anyhow::bail!(
+                "The bundle was built with {} of the toolchain `{}`, but the default toolchain is \
+                 rustc {}. Pass the dated nightly toolchain of rustc {} via \
+                 `--toolchain nightly-<YYYY-MM-DD>`",
+                compiler,
+                toolchain,
+                default,
+                version
+            ),
+        },
+        None => anyhow::bail!(
+            "The bundle was built with {} and does not record its toolchain, but the default \
+             toolchain is rustc {}. Pass the dated nightly toolchain of rustc {} via \
+             `--toolchain nightly-<YYYY-MM-DD>`",
+            compiler,
+            default,
+            version
+        ),
+    }
+}
+
+/// Returns the release channel of a rustc `version`.
+fn channel_of(version: &Version) -> Channel {
+    match version.pre.as_str().split('.').next() {
+        Some("nightly") => Channel::Nightly,
+        Some("beta") => Channel::Beta,
+        Some("dev") => Channel::Dev,
+        _ => Channel::Stable,
+    }
+}
+
+/// Returns the dated nightly toolchain of a rustup toolchain name, without the host triple,
+/// e.g. `nightly-2021-09-01` of `nightly-2021-09-01-x86_64-unknown-linux-gnu`.
+fn dated_nightly(toolchain: &str) -> Option<String> {
+    let date = toolchain.strip_prefix("nightly-")?.get(..10)?;
+    let is_date = date.char_indices().all(|(i, c)| match i {
+        4 | 7 => c == '-',
+        _ => c.is_ascii_digit(),
+    });
+    match is_date {
+        true => Some(format!("nightly-{}", date)),
+        false => None,
+    }
+}
+
+/// Ensures that the `local` wasm-opt is the one the bundle was optimized with, if recorded.
+///
+/// Different versions of wasm-opt optimize differently, so the code hashes would not match.
+fn assert_wasm_opt_version(recorded: Option<&str>, local: Option<&str>) -> Result<()> {
+    match recorded {
+        Some(recorded) if local != Some(recorded) => anyhow::bail!(
+            "The bundle was optimized with {}, but the installed wasm-opt is {}. Install that \
+             version of wasm-opt to verify the bundle",
+            recorded,
+            local.unwrap_or("missing")
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Parses a hex encoded code hash, returning it in the format used by the metadata.
+fn parse_code_hash(input: &str) -> Result<String> {
+    Ok(metadata::parse_code_hash(input)?.to_string())
+}
+
+/// Returns all build inputs of the `source` sections whose values differ.
+///
+/// Nested sections are compared key by key, the code hash and the Wasm are ignored.
+fn build_input_differences(
+    recorded: &Map<String, Value>,
+    local: &Map<String, Value>,
+) -> Vec<BuildInputDifference> {
+    let mut recorded_inputs = BTreeMap::new();
+    let mut local_inputs = BTreeMap::new();
+    flatten_inputs("", recorded, &mut recorded_inputs);
+    flatten_inputs("", local, &mut local_inputs);
+
+    let mut inputs = recorded_inputs.keys().cloned().collect::<Vec<_>>();
+    inputs.extend(local_inputs.keys().cloned());
+    inputs.sort();
+    inputs.dedup();
+    inputs
+        .into_iter()
+        .filter(|input| !SOURCE_OUTPUTS.contains(&input.as_str()))
+        .filter_map(|input| {
+            let recorded = recorded_inputs.get(&input).cloned();
+            let local = local_inputs.get(&input).cloned();
+            if recorded == local {
+                return None;
+            }
+            Some(BuildInputDifference {
+                input,
+                recorded,
+                local,
+            })
+        })
+        .collect()
+}
+
+/// Flattens nested sections into `inputs`, joining the keys with dots.
+fn flatten_inputs(
+    prefix: &str,
+    section: &Map<String, Value>,
+    inputs: &mut BTreeMap<String, String>,
+) {
+    for (key, value) in section {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            Value::Object(section) => flatten_inputs(&path, section, inputs),
+            Value::String(value) => {
+                inputs.insert(path, value.clone());
+            }
+            Value::Null => (),
+            value => {
+                inputs.insert(path, value.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        assert_wasm_opt_version, build_input_differences, channel_of, parse_code_hash,
+        recorded_toolchain, BuildInputDifference,
+    };
+    use crate::util;
+    use semver::Version;
+    use serde_json::{json, Map, Value};
+
+    fn section(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(section) => section,
+            _ => panic!("not a section"),
+        }
+    }
+
+    #[test]
+    fn code_hashes_are_normalized() {
+        let hash = format!("0x{}", "AB".repeat(32));
+
+        assert_eq!(
+            parse_code_hash(&hash).unwrap(),
+            format!("0x{}", "ab".repeat(32))
+        );
+        assert_eq!(
+            parse_code_hash(&"ab".repeat(32)).unwrap(),
+            format!("0x{}", "ab".repeat(32))
+        );
+        assert!(parse_code_hash("0x1234").is_err());
+        assert!(parse_code_hash(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn toolchain_is_derived_from_the_recorded_toolchain() {
+        let default = Version::parse("1.57.0-nightly").unwrap();
+        let recorded = "nightly-2021-09-01-x86_64-unknown-linux-gnu";
+
+        assert_eq!(
+            recorded_toolchain("rustc 1.57.0-nightly", Some(recorded), &default).unwrap(),
+            None
+        );
+        let toolchain = recorded_toolchain("rustc 1.56.0-nightly", Some(recorded), &default)
+            .unwrap()
+            .unwrap();
+        assert_eq!(toolchain, "nightly-2021-09-01");
+        // The rebuild checks the channel of the derived toolchain before invoking cargo.
+        let version = Version::parse("1.56.0-nightly").unwrap();
+        assert!(util::assert_nightly(channel_of(&version)).is_ok());
+
+        let err =
+            recorded_toolchain("rustc 1.56.0-nightly", Some("nightly"), &default).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The bundle was built with rustc 1.56.0-nightly of the toolchain `nightly`, but the \
+             default toolchain is rustc 1.57.0-nightly. Pass the dated nightly toolchain of rustc \
+             1.56.0-nightly via `--toolchain nightly-<YYYY-MM-DD>`"
+        );
+        assert!(recorded_toolchain("rustc 1.56.0-nightly", None, &default).is_err());
+        assert!(recorded_toolchain("gcc 11.0.0", None, &default).is_err());
+    }
+
+    #[test]
+    fn bundles_built_on_stable_are_rejected_up_front() {
+        let default = Version::parse("1.56.0").unwrap();
+
+        let err = recorded_toolchain("rustc 1.56.0", Some("stable"), &default).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "The bundle was built with rustc 1.56.0: cargo-contract cannot build using the \
+             \"stable\" channel. Switch to nightly. See \
+             https://github.com/paritytech/cargo-contract#build-requires-the-nightly-toolchain"
+        );
+        assert!(recorded_toolchain("rustc 1.56.0-beta.3", None, &default).is_err());
+    }
+
+    #[test]
+    fn differing_wasm_opt_versions_are_rejected() {
+        assert!(assert_wasm_opt_version(None, None).is_ok());
+        assert!(assert_wasm_opt_version(
+            Some("wasm-opt version 101"),
+            Some("wasm-opt version 101")
+        )
+        .is_ok());
+        let err =
+            assert_wasm_opt_version(Some("wasm-opt version 101"), Some("wasm-opt version 99"))
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The bundle was optimized with wasm-opt version 101, but the installed wasm-opt is \
+             wasm-opt version 99. Install that version of wasm-opt to verify the bundle"
+        );
+        assert!(assert_wasm_opt_version(Some("wasm-opt version 101"), None).is_err());
+    }
+
+    #[test]
+    fn differing_build_inputs_are_reported() {
+        let recorded = section(json!({
+            "hash": "0x01",
+            "language": "ink! 3.0.0-rc5",
+            "compiler": "rustc 1.56.0-nightly",
+            "wasm": "0x0061736d"
+        }));
+        let local = section(json!({
+            "hash": "0x02",
+            "language": "ink! 3.0.0-rc5",
+            "compiler": "rustc 1.57.0-nightly",
+            "build": { "mode": "release" }
+        }));
+
+        assert_eq!(
+            build_input_differences(&recorded, &local),
+            vec![
+                BuildInputDifference {
+                    input: "build.mode".into(),
+                    recorded: None,
+                    local: Some("release".into()),
+                },
+                BuildInputDifference {
+                    input: "compiler".into(),
+                    recorded: Some("rustc 1.56.0-nightly".into()),
+                    local: Some("rustc 1.57.0-nightly".into()),
+                },
+            ]
+        );
+    }
+}
@@ -14,37 +14,470 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::ManifestPath;
+use crate::{BuildMode, ManifestPath, OptimizationPasses};
 use anyhow::{Context, Result};
 use cargo_metadata::{Metadata as CargoMetadata, MetadataCommand, Package};
-use colored::Colorize;
 use semver::Version;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use serde_json::{Map, Value};
-use std::{fs, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter, Result as DisplayResult},
+    fs,
+    path::PathBuf,
+    str::FromStr,
+};
 use toml::value;
 use url::Url;
 
+/// VMs components can be deployed to, as named in the `vm` field of deploy entries, along with
+/// their aliases.
+pub const COMPOSABLE_VMS: &[(&str, &[&str])] = &[
+    ("pallet-contracts", &["wasm", "contracts"]),
+    ("runtime-gateway", &[]),
+];
+
+/// Gateways components can be executed through, as named in the `gateway` field of exec entries,
+/// along with their aliases.
+pub const COMPOSABLE_GATEWAYS: &[(&str, &[&str])] = &[
+    ("runtime-gateway", &["runtime"]),
+    ("contracts-gateway", &["contracts"]),
+];
+
+/// Returns the name of the VM named `vm` or one of its aliases, see [`COMPOSABLE_VMS`].
+pub fn resolve_vm(vm: &str) -> Result<&'static str> {
+    resolve_name(COMPOSABLE_VMS, vm, "VM")
+}
+
+/// Returns the name of the gateway named `gateway` or one of its aliases, see
+/// [`COMPOSABLE_GATEWAYS`].
+pub fn resolve_gateway(gateway: &str) -> Result<&'static str> {
+    resolve_name(COMPOSABLE_GATEWAYS, gateway, "gateway")
+}
+
+fn resolve_name(
+    names: &[(&'static str, &[&str])],
+    input: &str,
+    kind: &str,
+) -> Result<&'static str> {
+    if let Some((name, _)) = names
+        .iter()
+        .find(|(name, aliases)| *name == input || aliases.contains(&input))
+    {
+        return Ok(name);
+    }
+    let supported = names
+        .iter()
+        .map(|(name, aliases)| match aliases.is_empty() {
+            true => name.to_string(),
+            false => format!("{} ({})", name, aliases.join(", ")),
+        })
+        .collect::<Vec<_>>();
+    anyhow::bail!(
+        "Unknown {} '{}', supported {}s are: {}",
+        kind,
+        input,
+        kind,
+        supported.join(", ")
+    )
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ComposableDeployConfig {
     pub compose: String,
     pub vm: String,
-    pub url: String,
+    pub url: Url,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ComposableExecConfig {
     pub compose: String,
     pub gateway: String,
-    pub url: String,
+    pub url: Url,
+    /// Account requesting the execution, as public key or secret URI. Defaults to the signer.
+    pub requester: Option<String>,
+    /// Target account of the execution, as public key or secret URI. Defaults to the requester.
+    pub target: Option<String>,
+    /// Hex encoded input data. Defaults to `00`.
+    pub data: Option<String>,
+    /// Execution phase. Defaults to `0`, the execution phase.
+    pub phase: Option<u8>,
+    /// Value of balance transfer attached to the execution. Defaults to the value of the selected
+    /// network, `0` otherwise.
+    pub value: Option<u128>,
+    /// Maximum amount of gas to be used. Unless set here or by the selected network, it is
+    /// estimated via a dry run approximating the execution, or the gateway specific default if the
+    /// node cannot dry run it.
+    pub gas: Option<u64>,
+}
+
+/// Build settings of a component.
+///
+/// Settings of a component override those in `[package.metadata.contract]`, which in turn
+/// override the defaults of `composable-build`: release mode and `wasm-opt -O3`.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ComponentBuildConfig {
+    /// Cargo features enabled in addition to the feature named after the component.
+    pub features: Option<Vec<String>>,
+    /// Number of optimization passes passed to `wasm-opt`.
+    #[serde(default, deserialize_with = "deserialize_from_str")]
+    pub optimization_passes: Option<OptimizationPasses>,
+    /// Whether the contract is built with debug functionality.
+    #[serde(default, deserialize_with = "deserialize_from_str")]
+    pub build_mode: Option<BuildMode>,
+    /// Maximum number of memory pages the contract may allocate.
+    pub max_memory_pages: Option<u32>,
+    /// Do not remove symbols (Wasm name section) when optimizing.
+    pub keep_debug_symbols: Option<bool>,
+}
+
+impl ComponentBuildConfig {
+    /// Returns the settings of `self`, falling back to those of `defaults` where unset.
+    pub fn or(self, defaults: &ComponentBuildConfig) -> ComponentBuildConfig {
+        ComponentBuildConfig {
+            features: self.features.or_else(|| defaults.features.clone()),
+            optimization_passes: self.optimization_passes.or(defaults.optimization_passes),
+            build_mode: self.build_mode.or(defaults.build_mode),
+            max_memory_pages: self.max_memory_pages.or(defaults.max_memory_pages),
+            keep_debug_symbols: self.keep_debug_symbols.or(defaults.keep_debug_symbols),
+        }
+    }
+}
+
+/// Deserializes a value from its string representation, also accepting plain numbers.
+pub(crate) fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(u64),
+    }
+    let value = match Option::<StringOrNumber>::deserialize(deserializer)? {
+        Some(StringOrNumber::String(value)) => value,
+        Some(StringOrNumber::Number(value)) => value.to_string(),
+        None => return Ok(None),
+    };
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// A component of the composable schedule.
+///
+/// Either just the name of the component, or a table of its name and build settings:
+///
+/// ```toml
+/// composables = ["flipper", { name = "dex", features = ["oracle"], optimization-passes = "z" }]
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComposableEntry {
+    pub name: String,
+    pub build: ComponentBuildConfig,
+}
+
+impl From<&str> for ComposableEntry {
+    fn from(name: &str) -> Self {
+        ComposableEntry {
+            name: name.to_string(),
+            build: Default::default(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ComposableEntry {
+    /// Dispatches on the name and the table form, so errors in a table keep the path of the
+    /// offending key.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct EntryVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for EntryVisitor {
+            type Value = ComposableEntry;
+
+            fn expecting(&self, f: &mut Formatter) -> DisplayResult {
+                write!(
+                    f,
+                    "a component name or a table of its name and build settings"
+                )
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                Ok(ComposableEntry::from(name))
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let table = ComposableEntryTable::deserialize(
+                    serde::de::value::MapAccessDeserializer::new(map),
+                )?;
+                Ok(table.into())
+            }
+        }
+
+        deserializer.deserialize_any(EntryVisitor)
+    }
+}
+
+/// The table form of a [`ComposableEntry`].
+///
+/// Lists the fields of [`ComponentBuildConfig`] rather than flattening it, since unknown keys
+/// cannot be denied for flattened fields.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct ComposableEntryTable {
+    name: String,
+    features: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_from_str")]
+    optimization_passes: Option<OptimizationPasses>,
+    #[serde(default, deserialize_with = "deserialize_from_str")]
+    build_mode: Option<BuildMode>,
+    max_memory_pages: Option<u32>,
+    keep_debug_symbols: Option<bool>,
+}
+
+impl From<ComposableEntryTable> for ComposableEntry {
+    fn from(table: ComposableEntryTable) -> Self {
+        ComposableEntry {
+            name: table.name,
+            build: ComponentBuildConfig {
+                features: table.features,
+                optimization_passes: table.optimization_passes,
+                build_mode: table.build_mode,
+                max_memory_pages: table.max_memory_pages,
+                keep_debug_symbols: table.keep_debug_symbols,
+            },
+        }
+    }
+}
+
+/// Error in the composable schedule of `[package.metadata]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleError {
+    /// Path of the offending key, e.g. `deploy[2].url`.
+    pub path: String,
+    /// What is wrong with the key.
+    pub message: String,
+}
+
+impl ScheduleError {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        ScheduleError {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
 }
 
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
+        write!(
+            f,
+            "Invalid composable schedule, `{}` in [package.metadata]: {}",
+            self.path, self.message
+        )
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct ComposableScheduleMetadata {
-    pub composables: Vec<String>,
-    pub schedule: Option<String>,
+    pub composables: Vec<ComposableEntry>,
+    /// Dependencies between components: maps a component to the components it depends on.
+    ///
+    /// Dependencies are built and deployed before the components depending on them.
+    pub schedule: Option<BTreeMap<String, Vec<String>>>,
     pub deploy: Option<Vec<ComposableDeployConfig>>,
     pub exec: Option<Vec<ComposableExecConfig>>,
+    /// Standalone `.wat` or `.wasm` files of components, relative to the manifest directory.
+    pub sources: Option<BTreeMap<String, PathBuf>>,
+    /// Build settings shared by all components, from `[package.metadata.contract]`.
+    pub contract: Option<ComponentBuildConfig>,
+}
+
+impl ComposableScheduleMetadata {
+    /// Parses and validates the composable schedule from the `[package.metadata]` table.
+    pub fn from_package_metadata(metadata: &Value) -> Result<Self, ScheduleError> {
+        if metadata.get("composables").is_none() {
+            return Err(ScheduleError::new(
+                "composables",
+                "missing key, the components of the schedule must be listed",
+            ));
+        }
+        let schedule: Self = serde_path_to_error::deserialize(metadata)
+            .map_err(|err| ScheduleError::new(err.path().to_string(), err.inner().to_string()))?;
+        schedule.validate()?;
+        Ok(schedule)
+    }
+
+    /// Checks that the components are unique and that all references to components are listed
+    /// in `composables`.
+    pub fn validate(&self) -> Result<(), ScheduleError> {
+        for (i, compose) in self.composables.iter().enumerate() {
+            if let Some(first) = self.composables[..i]
+                .iter()
+                .position(|c| c.name == compose.name)
+            {
+                return Err(ScheduleError::new(
+                    format!("composables[{}]", i),
+                    format!(
+                        "duplicate component '{}', already listed at composables[{}]",
+                        compose.name, first
+                    ),
+                ));
+            }
+        }
+        let ensure_listed = |path: String, name: &str| {
+            if self.contains(name) {
+                Ok(())
+            } else {
+                Err(ScheduleError::new(
+                    path,
+                    format!("component '{}' is not listed in composables", name),
+                ))
+            }
+        };
+        for (compose, dependencies) in self.schedule.iter().flatten() {
+            ensure_listed(format!("schedule.{}", compose), compose)?;
+            for (i, dependency) in dependencies.iter().enumerate() {
+                ensure_listed(format!("schedule.{}[{}]", compose, i), dependency)?;
+            }
+        }
+        self.execution_order()
+            .map_err(|err| ScheduleError::new("schedule", err.to_string()))?;
+        for (i, deploy) in self.deploy.iter().flatten().enumerate() {
+            ensure_listed(format!("deploy[{}].compose", i), &deploy.compose)?;
+            resolve_vm(&deploy.vm)
+                .map_err(|err| ScheduleError::new(format!("deploy[{}].vm", i), err.to_string()))?;
+        }
+        for (i, exec) in self.exec.iter().flatten().enumerate() {
+            ensure_listed(format!("exec[{}].compose", i), &exec.compose)?;
+            resolve_gateway(&exec.gateway).map_err(|err| {
+                ScheduleError::new(format!("exec[{}].gateway", i), err.to_string())
+            })?;
+        }
+        for name in self.sources.iter().flat_map(BTreeMap::keys) {
+            ensure_listed(format!("sources.{}", name), name)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the names of all components.
+    pub fn names(&self) -> Vec<&str> {
+        self.composables.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    /// Returns `true` if the schedule contains a component of the supplied name.
+    pub fn contains(&self, compose: &str) -> bool {
+        self.composables.iter().any(|c| c.name == compose)
+    }
+
+    /// Returns the build settings of the component, merged over the shared settings.
+    pub fn build_config(&self, compose: &str) -> ComponentBuildConfig {
+        let build = self
+            .composables
+            .iter()
+            .find(|c| c.name == compose)
+            .map(|c| c.build.clone())
+            .unwrap_or_default();
+        match &self.contract {
+            Some(defaults) => build.or(defaults),
+            None => build,
+        }
+    }
+
+    /// Returns the components the supplied component depends on.
+    pub fn dependencies_of(&self, compose: &str) -> &[String] {
+        self.schedule
+            .as_ref()
+            .and_then(|schedule| schedule.get(compose))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns all components in an order in which each component comes after its dependencies.
+    ///
+    /// Apart from that the order of `composables` is preserved.
+    pub fn execution_order(&self) -> Result<Vec<String>> {
+        for (compose, dependencies) in self.schedule.iter().flatten() {
+            for name in std::iter::once(compose).chain(dependencies) {
+                if !self.contains(name) {
+                    anyhow::bail!(
+                        "Component '{}' in the composable schedule is not listed in composables",
+                        name
+                    );
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.composables.len());
+        let mut path = Vec::new();
+        for compose in &self.composables {
+            self.visit(&compose.name, &mut path, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /// Returns the supplied components and all of their transitive dependencies, in execution
+    /// order. All components are returned if none are supplied.
+    pub fn execution_order_of(&self, selected: &[String]) -> Result<Vec<String>> {
+        let order = self.execution_order()?;
+        if selected.is_empty() {
+            return Ok(order);
+        }
+        self.ensure_components(selected)?;
+
+        let mut required = Vec::new();
+        let mut path = Vec::new();
+        for compose in selected {
+            self.visit(compose, &mut path, &mut required)?;
+        }
+        Ok(order
+            .into_iter()
+            .filter(|compose| required.contains(compose))
+            .collect())
+    }
+
+    /// Ensures that all of the supplied components are listed in `composables`.
+    pub fn ensure_components(&self, names: &[String]) -> Result<()> {
+        if let Some(unknown) = names.iter().find(|name| !self.contains(name)) {
+            anyhow::bail!(
+                "Unknown component '{}', valid components are: {}",
+                unknown,
+                self.names().join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Depth-first visit of `compose`, appending it to `order` after its dependencies.
+    fn visit(&self, compose: &str, path: &mut Vec<String>, order: &mut Vec<String>) -> Result<()> {
+        if order.iter().any(|c| c == compose) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|c| c == compose) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(compose.to_string());
+            anyhow::bail!(
+                "Cyclic dependency in composable schedule: {}",
+                cycle.join(" -> ")
+            );
+        }
+        path.push(compose.to_string());
+        for dependency in self.dependencies_of(compose) {
+            self.visit(dependency, path, order)?;
+        }
+        path.pop();
+        order.push(compose.to_string());
+        Ok(())
+    }
 }
 
 /// Relevant metadata obtained from Cargo.toml.
@@ -54,7 +487,6 @@ pub struct CrateMetadata {
     pub cargo_meta: cargo_metadata::Metadata,
     pub package_name: String,
     pub contract_artifact_name: String,
-    pub t3rn_composable_schedule: Option<ComposableScheduleMetadata>,
     pub root_package: Package,
     pub original_wasm: PathBuf,
     pub target_directory: PathBuf,
@@ -92,28 +524,10 @@ impl CrateMetadata {
         dest_wasm.push(package_name.clone());
         dest_wasm.set_extension("wasm");
 
-        let mut composable_schedule: Option<ComposableScheduleMetadata> = None;
-
         let ink_version = metadata
             .packages
             .iter()
             .find_map(|package| {
-                // Extract the composable metadata which should be place in the Cargo.toml of package_name.
-                if package.name == package_name.clone() {
-                    composable_schedule = match serde_json::from_value(package.metadata.clone()) {
-                        Ok(composable_schedule) => Some(composable_schedule),
-                        Err(_) => None,
-                    };
-                    if let Some(t3rn_schedule) = composable_schedule.clone() {
-                        println!(
-                            "{} {:?}",
-                            "Detected t3rn schedule with following components:"
-                                .bright_blue()
-                                .bold(),
-                            t3rn_schedule.composables
-                        );
-                    }
-                }
                 if package.name == "ink_lang" {
                     Some(
                         Version::parse(&package.version.to_string())
@@ -139,11 +553,21 @@ impl CrateMetadata {
             homepage,
             user,
             contract_artifact_name: lib_name.to_string(),
-            t3rn_composable_schedule: composable_schedule,
             target_directory: metadata.target_directory.clone().into(),
         };
         Ok(crate_metadata)
     }
+
+    /// Parses and validates the t3rn composable schedule from `[package.metadata]`.
+    pub fn composable_schedule(&self) -> Result<ComposableScheduleMetadata> {
+        let schedule =
+            ComposableScheduleMetadata::from_package_metadata(&self.root_package.metadata)?;
+        log::info!(
+            "Detected t3rn schedule with following components: {:?}",
+            schedule.names()
+        );
+        Ok(schedule)
+    }
 }
 
 /// Get the result of `cargo metadata`, together with the root package id.
@@ -212,3 +636,266 @@ fn get_cargo_toml_metadata(
 
     Ok((documentation, homepage, user))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        resolve_gateway, resolve_vm, ComponentBuildConfig, ComposableScheduleMetadata,
+        ScheduleError,
+    };
+    use crate::{BuildMode, OptimizationPasses};
+
+    fn schedule(
+        composables: &[&str],
+        dependencies: &[(&str, &[&str])],
+    ) -> ComposableScheduleMetadata {
+        ComposableScheduleMetadata {
+            composables: composables.iter().map(|&c| c.into()).collect(),
+            schedule: Some(
+                dependencies
+                    .iter()
+                    .map(|(compose, deps)| {
+                        let deps = deps.iter().map(|d| d.to_string()).collect();
+                        (compose.to_string(), deps)
+                    })
+                    .collect(),
+            ),
+            deploy: None,
+            exec: None,
+            sources: None,
+            contract: None,
+        }
+    }
+
+    #[test]
+    fn execution_order_puts_dependencies_first() {
+        let schedule = schedule(
+            &["dex", "flipper", "token", "oracle"],
+            &[("dex", &["token", "oracle"]), ("oracle", &["token"])],
+        );
+
+        let order = schedule.execution_order().unwrap();
+
+        assert_eq!(order, vec!["token", "oracle", "dex", "flipper"]);
+        assert_eq!(schedule.dependencies_of("flipper"), &[] as &[String]);
+    }
+
+    #[test]
+    fn cyclic_dependencies_are_reported() {
+        let schedule = schedule(
+            &["flipper", "dex", "token"],
+            &[
+                ("dex", &["token"]),
+                ("token", &["flipper"]),
+                ("flipper", &["dex"]),
+            ],
+        );
+
+        let err = schedule.execution_order().unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Cyclic dependency in composable schedule: flipper -> dex -> token -> flipper"
+        );
+    }
+
+    #[test]
+    fn unknown_dependencies_are_reported() {
+        let schedule = schedule(&["dex"], &[("dex", &["token"])]);
+
+        let err = schedule.execution_order().unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Component 'token' in the composable schedule is not listed in composables"
+        );
+    }
+
+    #[test]
+    fn selected_components_are_ordered_with_their_dependencies() {
+        let schedule = schedule(
+            &["flipper", "dex", "token", "oracle"],
+            &[("dex", &["token"]), ("oracle", &["dex"])],
+        );
+
+        assert_eq!(
+            schedule.execution_order_of(&["dex".into()]).unwrap(),
+            vec!["token", "dex"]
+        );
+        assert_eq!(
+            schedule
+                .execution_order_of(&["flipper".into(), "token".into()])
+                .unwrap(),
+            vec!["flipper", "token"]
+        );
+        assert_eq!(
+            schedule.execution_order_of(&[]).unwrap(),
+            schedule.execution_order().unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_selected_components_are_reported() {
+        let schedule = schedule(&["flipper", "dex"], &[]);
+
+        let err = schedule
+            .execution_order_of(&["dex".into(), "flip".into()])
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown component 'flip', valid components are: flipper, dex"
+        );
+    }
+
+    #[test]
+    fn component_build_config_is_merged_over_contract_defaults() {
+        let schedule: ComposableScheduleMetadata = serde_json::from_value(serde_json::json!({
+            "composables": [
+                "flipper",
+                { "name": "dex", "features": ["oracle"], "optimization-passes": 3, "build-mode": "debug" }
+            ],
+            "contract": { "optimization-passes": "z", "max-memory-pages": 32 }
+        }))
+        .unwrap();
+
+        assert_eq!(schedule.names(), vec!["flipper", "dex"]);
+        assert_eq!(
+            schedule.build_config("dex"),
+            ComponentBuildConfig {
+                features: Some(vec!["oracle".to_string()]),
+                optimization_passes: Some(OptimizationPasses::Three),
+                build_mode: Some(BuildMode::Debug),
+                max_memory_pages: Some(32),
+                keep_debug_symbols: None,
+            }
+        );
+        assert_eq!(
+            schedule.build_config("flipper"),
+            ComponentBuildConfig {
+                optimization_passes: Some(OptimizationPasses::Z),
+                max_memory_pages: Some(32),
+                ..Default::default()
+            }
+        );
+    }
+
+    fn schedule_error(metadata: serde_json::Value) -> ScheduleError {
+        ComposableScheduleMetadata::from_package_metadata(&metadata).unwrap_err()
+    }
+
+    #[test]
+    fn missing_composables_are_reported() {
+        let err = schedule_error(serde_json::json!({ "contract": { "max-memory-pages": 32 } }));
+
+        assert_eq!(err.path, "composables");
+        assert_eq!(
+            err.to_string(),
+            "Invalid composable schedule, `composables` in [package.metadata]: missing key, the components of the schedule must be listed"
+        );
+    }
+
+    #[test]
+    fn malformed_keys_are_reported_with_their_path() {
+        let err = schedule_error(serde_json::json!({
+            "composables": ["flipper", "dex"],
+            "deploy": [
+                { "compose": "flipper", "vm": "pallet-contracts", "url": "ws://localhost:9944" },
+                { "compose": "dex", "vm": "pallet-contracts", "url": "ws://localhost:9944" },
+                { "compose": "dex", "vm": "pallet-contracts", "url": 9944 }
+            ]
+        }));
+        assert_eq!(err.path, "deploy[2].url");
+
+        let err = schedule_error(serde_json::json!({
+            "composables": ["flipper", { "name": "dex", "max-memory-pages": "many" }]
+        }));
+        assert_eq!(err.path, "composables[1].max-memory-pages");
+
+        let err = schedule_error(serde_json::json!({
+            "composables": [{ "name": "dex", "optimization-passes": "9" }]
+        }));
+        assert_eq!(err.path, "composables[0].optimization-passes");
+        assert!(
+            err.message.contains("Unknown optimization passes"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn misspelled_component_settings_are_reported() {
+        let err = schedule_error(serde_json::json!({
+            "composables": ["flipper", { "name": "dex", "feature": ["oracle"] }]
+        }));
+
+        assert_eq!(err.path, "composables[1].feature");
+        assert!(
+            err.message.starts_with("unknown field `feature`"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn duplicate_components_are_reported() {
+        let err = schedule_error(serde_json::json!({
+            "composables": ["flipper", "dex", { "name": "flipper" }]
+        }));
+
+        assert_eq!(err.path, "composables[2]");
+        assert_eq!(
+            err.message,
+            "duplicate component 'flipper', already listed at composables[0]"
+        );
+    }
+
+    #[test]
+    fn references_to_unlisted_components_are_reported() {
+        let err = schedule_error(serde_json::json!({
+            "composables": ["flipper", "dex"],
+            "schedule": { "dex": ["flipper", "token"] }
+        }));
+        assert_eq!(err.path, "schedule.dex[1]");
+        assert_eq!(
+            err.message,
+            "component 'token' is not listed in composables"
+        );
+
+        let err = schedule_error(serde_json::json!({
+            "composables": ["flipper"],
+            "exec": [{ "compose": "dex", "gateway": "runtime", "url": "ws://localhost:9944" }]
+        }));
+        assert_eq!(err.path, "exec[0].compose");
+    }
+
+    #[test]
+    fn unknown_vms_and_gateways_are_reported() {
+        let err = schedule_error(serde_json::json!({
+            "composables": ["flipper"],
+            "deploy": [{ "compose": "flipper", "vm": "evm", "url": "ws://localhost:9944" }]
+        }));
+        assert_eq!(err.path, "deploy[0].vm");
+        assert_eq!(
+            err.message,
+            "Unknown VM 'evm', supported VMs are: pallet-contracts (wasm, contracts), runtime-gateway"
+        );
+
+        let err = schedule_error(serde_json::json!({
+            "composables": ["flipper"],
+            "exec": [{ "compose": "flipper", "gateway": "evm", "url": "ws://localhost:9944" }]
+        }));
+        assert_eq!(err.path, "exec[0].gateway");
+    }
+
+    #[test]
+    fn vms_and_gateways_are_resolved_by_name_and_alias() {
+        assert_eq!(resolve_vm("wasm").unwrap(), "pallet-contracts");
+        assert_eq!(resolve_vm("runtime-gateway").unwrap(), "runtime-gateway");
+        assert_eq!(resolve_gateway("runtime").unwrap(), "runtime-gateway");
+        assert_eq!(
+            resolve_gateway("contracts-gateway").unwrap(),
+            "contracts-gateway"
+        );
+    }
+}
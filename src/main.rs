@@ -16,13 +16,18 @@
 
 mod cmd;
 mod crate_metadata;
+#[cfg(feature = "extrinsics")]
+mod network;
 mod util;
 mod validate_wasm;
 mod workspace;
 
 use self::workspace::ManifestPath;
 
-use crate::cmd::{metadata::MetadataResult, BuildCommand, CheckCommand, TestCommand};
+use crate::cmd::{
+    metadata::MetadataResult, BuildCommand, CheckCommand, InspectCommand, TestCommand,
+    VerifyCommand,
+};
 
 #[cfg(feature = "extrinsics")]
 use sp_core::{
@@ -43,8 +48,6 @@ use anyhow::{Error, Result};
 use colored::Colorize;
 use structopt::{clap, StructOpt};
 
-use crate::crate_metadata::CrateMetadata;
-
 #[derive(Debug, StructOpt)]
 #[structopt(bin_name = "cargo")]
 #[structopt(version = env!("CARGO_CONTRACT_CLI_IMPL_VERSION"))]
@@ -67,35 +70,256 @@ pub(crate) struct ContractArgs {
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub(crate) struct HexData(pub Vec<u8>);
 
-#[cfg(feature = "extrinsics")]
 impl std::str::FromStr for HexData {
     type Err = hex::FromHexError;
 
     fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
-        hex::decode(input).map(HexData)
+        hex::decode(input.trim_start_matches("0x")).map(HexData)
     }
 }
 
 /// Arguments required for creating and sending an extrinsic to a substrate node
 #[cfg(feature = "extrinsics")]
-#[derive(Debug, StructOpt)]
+#[derive(Debug)]
 pub(crate) struct ExtrinsicOpts {
     /// Websockets url of a substrate node
-    #[structopt(
-        name = "url",
-        long,
-        parse(try_from_str),
-        default_value = "ws://localhost:9944"
-    )]
     url: url::Url,
     /// Secret key URI for the account deploying the contract.
-    #[structopt(name = "suri", long, short)]
     suri: String,
     /// Password for the secret key
+    password: Option<String>,
+}
+
+/// Node and signer of an extrinsic, supplied directly or by a network profile
+#[cfg(feature = "extrinsics")]
+#[derive(Debug, StructOpt)]
+pub(crate) struct NetworkOpts {
+    /// Name of a network of `[package.metadata.contract.networks]` or `~/.cargo-contract.toml`
+    /// supplying the node, signer, gas and value unless passed explicitly
+    #[structopt(long)]
+    network: Option<String>,
+    /// Path to the Cargo.toml whose `[package.metadata.contract.networks]` are used
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Websockets url of a substrate node [default: ws://localhost:9944]
+    #[structopt(name = "url", long, parse(try_from_str))]
+    url: Option<url::Url>,
+    /// Secret key URI for the account deploying the contract.
+    #[structopt(name = "suri", long, short)]
+    suri: Option<String>,
+    /// Password for the secret key
     #[structopt(name = "password", long, short)]
     password: Option<String>,
 }
 
+#[cfg(feature = "extrinsics")]
+impl NetworkOpts {
+    /// Returns the profile of the selected network, an empty profile if none is selected.
+    pub fn network(&self) -> Result<network::NetworkConfig> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        network::select(&manifest_path, self.network.as_deref())
+    }
+
+    /// Returns the extrinsic options, the flags taking precedence over the network profile.
+    pub fn extrinsic_opts(&self, network: &network::NetworkConfig) -> Result<ExtrinsicOpts> {
+        let url = match self.url.as_ref().or_else(|| network.url.as_ref()) {
+            Some(url) => url.clone(),
+            None => url::Url::parse("ws://localhost:9944")?,
+        };
+        let suri = match self.suri.as_ref().or_else(|| network.suri.as_ref()) {
+            Some(suri) => suri.clone(),
+            None => anyhow::bail!("Either --suri or a --network with a suri must be supplied"),
+        };
+        Ok(ExtrinsicOpts {
+            url,
+            suri,
+            password: self.password.clone().or_else(|| network.password.clone()),
+        })
+    }
+}
+
+/// Input data of a contract constructor or message, either supplied raw or encoded from the
+/// contract metadata
+#[cfg(feature = "extrinsics")]
+#[derive(Debug, Clone, StructOpt)]
+pub(crate) struct CallDataOpts {
+    /// Hex encoded input data
+    #[structopt(long, conflicts_with_all = &["message", "constructor"])]
+    data: Option<HexData>,
+    /// Name of the constructor to call, its selector and argument types are looked up in the
+    /// contract metadata
+    #[structopt(long, conflicts_with = "message")]
+    constructor: Option<String>,
+    /// Name of the message to call, its selector and argument types are looked up in the
+    /// contract metadata
+    #[structopt(long)]
+    message: Option<String>,
+    /// Arguments of the constructor or message. Primitives are supplied as is, other types as
+    /// JSON, e.g. `--args 42 '[1, 2]' '{"Some": true}'`
+    #[structopt(long, min_values = 1)]
+    args: Vec<String>,
+    /// Path to the `metadata.json` or `<name>.contract` bundle of the contract, used to encode the
+    /// input and decode the emitted events. Defaults to the metadata of the current project
+    #[structopt(long, parse(from_os_str))]
+    metadata: Option<PathBuf>,
+}
+
+#[cfg(feature = "extrinsics")]
+impl CallDataOpts {
+    /// Returns the input data of a call to a constructor, i.e. of an instantiation.
+    pub fn constructor_data(&self) -> Result<HexData> {
+        if self.message.is_some() {
+            anyhow::bail!("Contracts are instantiated via a constructor, use --constructor");
+        }
+        match (&self.data, &self.constructor) {
+            (Some(data), _) => Ok(data.clone()),
+            (None, Some(constructor)) => Ok(HexData(
+                self.abi()?.encode_constructor(constructor, &self.args)?,
+            )),
+            (None, None) => anyhow::bail!("Either --data or --constructor must be supplied"),
+        }
+    }
+
+    /// Returns the input data of a call to a message, `default_data` if none is supplied.
+    pub fn message_data(&self, default_data: HexData) -> Result<HexData> {
+        if self.constructor.is_some() {
+            anyhow::bail!("Calls are dispatched to a message, use --message");
+        }
+        match (&self.data, &self.message) {
+            (Some(data), _) => Ok(data.clone()),
+            (None, Some(message)) => Ok(HexData(self.abi()?.encode_message(message, &self.args)?)),
+            (None, None) => Ok(default_data),
+        }
+    }
+
+    /// Decodes the value returned by the called message, if the message and the contract metadata
+    /// are known.
+    pub fn return_value(&self, data: &[u8]) -> Result<Option<serde_json::Value>> {
+        match (&self.message, self.decoding_abi()?) {
+            (Some(message), Some(abi)) => Ok(Some(abi.decode_return(message, data)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the ABI to decode the emitted events with.
+    ///
+    /// Without `--metadata`, the metadata of the current project is used if it is available.
+    pub fn decoding_abi(&self) -> Result<Option<cmd::contract_abi::ContractAbi>> {
+        match &self.metadata {
+            Some(_) => self.abi().map(Some),
+            None => match self.abi() {
+                Ok(abi) => Ok(Some(abi)),
+                Err(err) => {
+                    log::info!("No contract metadata to decode events with: {:#}", err);
+                    Ok(None)
+                }
+            },
+        }
+    }
+
+    fn abi(&self) -> Result<cmd::contract_abi::ContractAbi> {
+        let path = match &self.metadata {
+            Some(path) => path.clone(),
+            None => {
+                let crate_metadata = crate_metadata::CrateMetadata::collect(&Default::default())?;
+                crate_metadata
+                    .target_directory
+                    .join(cmd::metadata::METADATA_FILE)
+            }
+        };
+        cmd::contract_abi::ContractAbi::load(&path)
+    }
+}
+
+/// Safety margin in percent added to estimated gas limits, unless `--gas-margin` is passed.
+#[cfg(feature = "extrinsics")]
+const DEFAULT_GAS_MARGIN: &str = "10";
+
+/// Gas limit of a contract call or instantiation, estimated via a dry run unless supplied
+#[cfg(feature = "extrinsics")]
+#[derive(Debug, StructOpt)]
+pub(crate) struct GasOpts {
+    /// Maximum amount of gas to be used for this command. If omitted, the gas consumed by a dry
+    /// run of the command plus `--gas-margin` is used
+    #[structopt(name = "gas", long)]
+    gas_limit: Option<u64>,
+    /// Safety margin in percent added to the estimated gas
+    #[structopt(long, default_value = DEFAULT_GAS_MARGIN)]
+    gas_margin: u64,
+}
+
+#[cfg(feature = "extrinsics")]
+impl GasOpts {
+    /// Returns the options, falling back to the gas limit of the network profile.
+    pub fn or(&self, network: &network::NetworkConfig) -> GasOpts {
+        GasOpts {
+            gas_limit: self.gas_limit.or(network.gas),
+            gas_margin: self.gas_margin,
+        }
+    }
+
+    /// Returns the options of a gas limit which is not passed on the command line, e.g. the one
+    /// of an entry of the composable schedule, estimated with the default `--gas-margin`.
+    pub fn from_limit(gas_limit: Option<u64>) -> GasOpts {
+        GasOpts {
+            gas_limit,
+            gas_margin: DEFAULT_GAS_MARGIN
+                .parse()
+                .expect("the default gas margin is a number; qed"),
+        }
+    }
+
+    /// Returns the supplied gas limit, or estimates it from a dry run with the maximum gas limit.
+    pub fn limit<F>(&self, dry_run: F) -> Result<u64>
+    where
+        F: FnOnce(u64) -> Result<cmd::dry_run::DryRunResult>,
+    {
+        if let Some(gas_limit) = self.gas_limit {
+            return Ok(gas_limit);
+        }
+        let result = dry_run(cmd::dry_run::ESTIMATION_GAS_LIMIT)?;
+        let gas_limit = cmd::dry_run::estimate_gas(&result, self.gas_margin)?;
+        // Printed to stderr, since stdout only holds the JSON result with `--output-json`.
+        eprintln!(
+            "{} {} ({}% margin), pass `--gas {}` to pin it",
+            "Estimated gas limit:".bright_blue().bold(),
+            gas_limit,
+            self.gas_margin,
+            gas_limit
+        );
+        Ok(gas_limit)
+    }
+
+    /// Returns the gas limit of a gateway call, see [`GasOpts::limit`].
+    ///
+    /// The gateway calls are estimated via an approximating dry run, see
+    /// [`cmd::dry_run::gateway_call`]. If the node does not provide the RPC of that dry run, the
+    /// `fallback` gas limit is used instead.
+    pub fn gateway_limit<F>(&self, fallback: u64, dry_run: F) -> Result<u64>
+    where
+        F: FnOnce(u64) -> Result<cmd::dry_run::DryRunResult>,
+    {
+        match self.limit(dry_run) {
+            Err(err) if cmd::dry_run::is_rpc_unavailable(&err) => {
+                eprintln!(
+                    "{} the node cannot dry run the call to estimate its gas ({:#}), using the \
+                     default gas limit {}",
+                    "warning:".yellow().bold(),
+                    err,
+                    fallback
+                );
+                Ok(fallback)
+            }
+            result => result,
+        }
+    }
+
+    /// Returns the gas limit of a dry run.
+    pub fn dry_run_limit(&self) -> u64 {
+        self.gas_limit.unwrap_or(cmd::dry_run::ESTIMATION_GAS_LIMIT)
+    }
+}
+
 #[cfg(feature = "extrinsics")]
 impl ExtrinsicOpts {
     pub fn signer(&self) -> Result<PairSigner<subxt::ContractsTemplateRuntime, sr25519::Pair>> {
@@ -325,6 +549,18 @@ impl Display for BuildMode {
     }
 }
 
+impl std::str::FromStr for BuildMode {
+    type Err = Error;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "debug" => Ok(BuildMode::Debug),
+            "release" => Ok(BuildMode::Release),
+            _ => anyhow::bail!("Unknown build mode {}", input),
+        }
+    }
+}
+
 /// The type of output to display at the end of a build.
 pub enum OutputType {
     /// Output build results in a human readable format.
@@ -469,10 +705,55 @@ enum Command {
     /// Compiles all of the composable smart contracts described in the schedule
     #[structopt(name = "composable-build")]
     ComposableBuild {
+        /// Path to the Cargo.toml of the composable contract to build
+        #[structopt(long, parse(from_os_str))]
+        manifest_path: Option<PathBuf>,
+        /// Only build the named component and its dependencies, may be passed multiple times
+        #[structopt(long = "compose", value_name = "name", number_of_values = 1)]
+        compose: Vec<String>,
         #[structopt(flatten)]
         verbosity: VerbosityFlags,
         #[structopt(flatten)]
         unstable_options: UnstableOptions,
+        /// Number of components to build concurrently
+        #[structopt(long, short, default_value = "1")]
+        jobs: usize,
+        /// Continue building the remaining components after a component failed to build
+        #[structopt(long)]
+        keep_going: bool,
+        /// Rebuild all components, even if their inputs did not change since the last build
+        #[structopt(long)]
+        force: bool,
+        /// Which build artifacts to generate for the ink! components.
+        ///
+        /// - `all`: Generate the Wasm, the metadata and a bundled `<component>.contract` file.
+        ///
+        /// - `code-only`: Only the Wasm is created, generation of metadata and a bundled
+        ///   `<component>.contract` file is skipped.
+        #[structopt(
+            long = "generate",
+            default_value = "all",
+            value_name = "all | code-only",
+            verbatim_doc_comment
+        )]
+        build_artifact: BuildArtifacts,
+        /// Build all components without debug functionality, overriding the `build-mode` of the
+        /// individual components. See `cargo contract build --help`.
+        #[structopt(long = "--release")]
+        build_release: bool,
+        /// Number of optimization passes passed to wasm-opt for all components, overriding the
+        /// `optimization-passes` of the schedule. See `cargo contract build --help`.
+        #[structopt(long)]
+        optimization_passes: Option<OptimizationPasses>,
+        /// Do not remove symbols (Wasm name section) when optimizing.
+        #[structopt(long)]
+        keep_debug_symbols: bool,
+        /// Export the build output in JSON format.
+        #[structopt(long, conflicts_with = "verbose")]
+        output_json: bool,
+        /// Print the resolved build plan of all components without building anything
+        #[structopt(long)]
+        dry_run: bool,
     },
     /// Generate contract metadata artifacts
     #[structopt(name = "generate-metadata")]
@@ -488,12 +769,18 @@ enum Command {
     /// Test the smart contract off-chain
     #[structopt(name = "test")]
     Test(TestCommand),
+    /// Rebuild the contract from source and check that it matches a code hash
+    #[structopt(name = "verify")]
+    Verify(VerifyCommand),
+    /// Print the contents of a contract bundle and optionally extract its Wasm or metadata
+    #[structopt(name = "inspect")]
+    Inspect(InspectCommand),
     /// Upload the smart contract code to the chain
     #[cfg(feature = "extrinsics")]
     #[structopt(name = "deploy")]
     Deploy {
         #[structopt(flatten)]
-        extrinsic_opts: ExtrinsicOpts,
+        network_opts: NetworkOpts,
         /// Path to wasm contract code, defaults to `./target/ink/<name>.wasm`
         #[structopt(parse(from_os_str))]
         wasm_path: Option<PathBuf>,
@@ -502,35 +789,100 @@ enum Command {
     #[cfg(feature = "extrinsics")]
     #[structopt(name = "composable-deploy")]
     ComposableDeploy {
+        /// Path to the Cargo.toml of the composable contract to deploy
+        #[structopt(long, parse(from_os_str))]
+        manifest_path: Option<PathBuf>,
+        /// Only deploy the named component and its dependencies, may be passed multiple times
+        #[structopt(long = "compose", value_name = "name", number_of_values = 1)]
+        compose: Vec<String>,
+        /// Name of a network of `[package.metadata.contract.networks]` or `~/.cargo-contract.toml`
+        /// supplying the signer unless passed explicitly
+        #[structopt(long)]
+        network: Option<String>,
         /// Secret key URI for the account deploying the contract.
         #[structopt(name = "suri", long, short)]
-        suri: String,
+        suri: Option<String>,
+        /// Password for the secret key
+        #[structopt(name = "password", long, short)]
+        password: Option<String>,
+        /// Print the node URL and VM of every entry without deploying anything
+        #[structopt(long)]
+        dry_run: bool,
+        /// Export the deploy plan in JSON format, only used with `--dry-run`.
+        #[structopt(long, requires = "dry-run")]
+        output_json: bool,
+    },
+    /// Execute the smart contracts selected in the exec section of the composable schedule via their gateways.
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "composable-exec")]
+    ComposableExec {
+        /// Path to the Cargo.toml of the composable contract to execute
+        #[structopt(long, parse(from_os_str))]
+        manifest_path: Option<PathBuf>,
+        /// Name of a network of `[package.metadata.contract.networks]` or `~/.cargo-contract.toml`
+        /// supplying the signer unless passed explicitly, and the gas and value of entries which
+        /// do not set them
+        #[structopt(long)]
+        network: Option<String>,
+        /// Secret key URI for the account executing the contracts.
+        #[structopt(name = "suri", long, short)]
+        suri: Option<String>,
+        /// Password for the secret key
+        #[structopt(name = "password", long, short)]
+        password: Option<String>,
+    },
+    /// Check the composable schedule of a contract without building anything.
+    #[structopt(name = "composable-validate")]
+    ComposableValidate {
+        /// Path to the Cargo.toml of the composable contract to validate
+        #[structopt(long, parse(from_os_str))]
+        manifest_path: Option<PathBuf>,
     },
     /// Instantiate a deployed smart contract
     #[cfg(feature = "extrinsics")]
     #[structopt(name = "instantiate")]
     Instantiate {
         #[structopt(flatten)]
-        extrinsic_opts: ExtrinsicOpts,
+        network_opts: NetworkOpts,
         /// Transfers an initial balance to the instantiated contract
         #[structopt(name = "endowment", long, default_value = "0")]
         endowment: u128,
-        /// Maximum amount of gas to be used for this command
-        #[structopt(name = "gas", long, default_value = "500000000")]
-        gas_limit: u64,
+        #[structopt(flatten)]
+        gas: GasOpts,
         /// The hash of the smart contract code already uploaded to the chain
-        #[structopt(long, parse(try_from_str = parse_code_hash))]
-        code_hash: H256,
-        /// Hex encoded data to call a contract constructor
+        #[structopt(
+            long,
+            parse(try_from_str = cmd::metadata::parse_code_hash),
+            required_unless = "code"
+        )]
+        code_hash: Option<contract_metadata::CodeHash>,
+        /// Path to the Wasm code or `<name>.contract` bundle to instantiate. The code is uploaded
+        /// first, unless it is already stored on chain
+        #[structopt(long, parse(from_os_str), conflicts_with = "code-hash")]
+        code: Option<PathBuf>,
+        /// Hex encoded salt from which the contract address is derived, see `cargo contract
+        /// address`. Requires a runtime supporting salted instantiation
+        #[structopt(long)]
+        salt: Option<HexData>,
+        #[structopt(flatten)]
+        call_data: CallDataOpts,
+        /// Execute the constructor via the contracts RPC of the node, without submitting a
+        /// transaction
         #[structopt(long)]
-        data: HexData,
+        dry_run: bool,
+        /// Export the dry run result in JSON format, only used with `--dry-run`.
+        #[structopt(long, requires = "dry-run")]
+        output_json: bool,
     },
+    /// Compute the address of a contract from its deployer, code hash and salt
+    #[structopt(name = "address")]
+    Address(cmd::address::AddressCommand),
     /// Call for smart contract execution on Runtime Gateway
     #[cfg(feature = "extrinsics")]
     #[structopt(name = "call-runtime-gateway")]
     CallRuntimeGateway {
         #[structopt(flatten)]
-        extrinsic_opts: ExtrinsicOpts,
+        network_opts: NetworkOpts,
         /// Target chain destination
         #[structopt(name = "target", long, short)]
         target: String,
@@ -540,25 +892,23 @@ enum Command {
         /// Execution Phase
         #[structopt(name = "phase", long, default_value = "0")]
         phase: u8,
-        /// Value of balance transfer optionally attached to the execution order
-        #[structopt(name = "value", long, default_value = "0")]
-        value: u128,
-        /// Maximum amount of gas to be used for this command
-        #[structopt(name = "gas", long, default_value = "500000000")]
-        gas_limit: u64,
+        /// Value of balance transfer optionally attached to the execution order [default: 0]
+        #[structopt(name = "value", long)]
+        value: Option<u128>,
+        #[structopt(flatten)]
+        gas: GasOpts,
         /// Path to wasm contract code, defaults to ./target/<name>-pruned.wasm
         #[structopt(parse(from_os_str))]
         wasm_path: Option<PathBuf>,
-        /// Hex encoded data to call a contract constructor
-        #[structopt(long, default_value = "00")]
-        data: HexData,
+        #[structopt(flatten)]
+        call_data: CallDataOpts,
     },
     /// Call for smart contract execution on Runtime Gateway
     #[cfg(feature = "extrinsics")]
     #[structopt(name = "call-contracts-gateway")]
     CallContractsGateway {
         #[structopt(flatten)]
-        extrinsic_opts: ExtrinsicOpts,
+        network_opts: NetworkOpts,
         /// Target chain destination
         #[structopt(long, default_value = "00")]
         target: HexData,
@@ -568,49 +918,59 @@ enum Command {
         /// Execution Phase
         #[structopt(name = "phase", long, default_value = "0")]
         phase: u8,
-        /// Value of balance transfer optionally attached to the execution order
-        #[structopt(name = "value", long, default_value = "0")]
-        value: u128,
-        /// Maximum amount of gas to be used for this command
-        #[structopt(name = "gas", long, default_value = "3875000000")]
-        gas_limit: u64,
+        /// Value of balance transfer optionally attached to the execution order [default: 0]
+        #[structopt(name = "value", long)]
+        value: Option<u128>,
+        #[structopt(flatten)]
+        gas: GasOpts,
         /// Path to wasm contract code, defaults to ./target/<name>-pruned.wasm
         #[structopt(parse(from_os_str))]
         wasm_path: Option<PathBuf>,
-        /// Hex encoded data to call a contract constructor
-        #[structopt(long, default_value = "00")]
-        data: HexData,
+        #[structopt(flatten)]
+        call_data: CallDataOpts,
+        /// Export the call result in JSON format.
+        #[structopt(long)]
+        output_json: bool,
     },
     /// Call a regular smart contract execution via Contracts Pallet Call
     #[cfg(feature = "extrinsics")]
     #[structopt(name = "call-contract")]
     CallContract {
         #[structopt(flatten)]
-        extrinsic_opts: ExtrinsicOpts,
+        network_opts: NetworkOpts,
         /// Target chain destination
         #[structopt(long, default_value = "00")]
         target: HexData,
-        /// Value of balance transfer optionally attached to the execution order
-        #[structopt(name = "value", long, default_value = "0")]
-        value: u128,
-        /// Maximum amount of gas to be used for this command
-        #[structopt(name = "gas", long, default_value = "3875000000")]
-        gas_limit: u64,
-        /// Hex encoded data to call a contract constructor
-        #[structopt(long, default_value = "00")]
-        data: HexData,
+        /// Value of balance transfer optionally attached to the execution order [default: 0]
+        #[structopt(name = "value", long)]
+        value: Option<u128>,
+        #[structopt(flatten)]
+        gas: GasOpts,
+        #[structopt(flatten)]
+        call_data: CallDataOpts,
+        /// Execute the message via the contracts RPC of the node, without submitting a
+        /// transaction. Prints the decoded return value and the gas consumed
+        #[structopt(long)]
+        dry_run: bool,
+        /// Export the call result in JSON format.
+        #[structopt(long)]
+        output_json: bool,
     },
 }
 
+/// Returns the secret key URI and password of the composable commands' signer, the flags taking
+/// precedence over the network profile.
 #[cfg(feature = "extrinsics")]
-fn parse_code_hash(input: &str) -> Result<H256> {
-    let bytes = hex::decode(input)?;
-    if bytes.len() != 32 {
-        anyhow::bail!("Code hash should be 32 bytes in length")
-    }
-    let mut arr = [0u8; 32];
-    arr.copy_from_slice(&bytes);
-    Ok(H256(arr))
+fn composable_signer(
+    network: &network::NetworkConfig,
+    suri: &Option<String>,
+    password: &Option<String>,
+) -> Result<(String, Option<String>)> {
+    let suri = match suri.clone().or_else(|| network.suri.clone()) {
+        Some(suri) => suri,
+        None => anyhow::bail!("Either --suri or a --network with a suri must be supplied"),
+    };
+    Ok((suri, password.clone().or_else(|| network.password.clone())))
 }
 
 fn main() {
@@ -670,105 +1030,277 @@ fn exec(cmd: Command) -> Result<Option<String>> {
                 Ok(None)
             }
         }
+        Command::Verify(verify) => {
+            let result = verify.exec()?;
+            if !result.is_match() {
+                println!("{}", result.display());
+                anyhow::bail!("Verification of the contract failed");
+            }
+            Ok(Some(result.display()))
+        }
+        Command::Inspect(inspect) => {
+            let result = inspect.exec()?;
+            Ok(Some(result.display()))
+        }
         Command::ComposableBuild {
+            manifest_path,
+            compose,
             verbosity,
             unstable_options,
+            jobs,
+            keep_going,
+            force,
+            build_artifact,
+            build_release,
+            optimization_passes,
+            keep_debug_symbols,
+            output_json,
+            dry_run,
         } => {
-            let manifest_path = Default::default();
-            let dest_wasm = cmd::composable_build::execute(
-                &manifest_path,
-                verbosity.try_into()?,
-                unstable_options.try_into()?,
-            )?;
-            Ok(Some(format!(
-                "\nYour composable contract(s) is/are ready. You can find it the following directory:\n{:?}",
-                dest_wasm.display().to_string().bold()
-            )))
+            let manifest_path = ManifestPath::try_from(manifest_path.as_ref())?;
+            let overrides = crate_metadata::ComponentBuildConfig {
+                build_mode: Some(BuildMode::Release).filter(|_| *build_release),
+                optimization_passes: *optimization_passes,
+                keep_debug_symbols: Some(true).filter(|_| *keep_debug_symbols),
+                ..Default::default()
+            };
+            // We want to ensure that the only thing in `STDOUT` is our JSON formatted string.
+            let verbosity = match output_json {
+                true => Verbosity::Quiet,
+                false => verbosity.try_into()?,
+            };
+            let args = cmd::composable_build::ExecuteArgs {
+                manifest_path,
+                verbosity,
+                build_artifact: *build_artifact,
+                unstable_flags: unstable_options.try_into()?,
+                overrides,
+                jobs: *jobs,
+                keep_going: *keep_going,
+                force: *force,
+                compose: compose.clone(),
+            };
+            if *dry_run {
+                let plan = cmd::composable_build::plan(&args)?;
+                return match output_json {
+                    true => Ok(Some(plan.serialize_json()?)),
+                    false => Ok(Some(plan.display())),
+                };
+            }
+            let result = cmd::composable_build::execute(args)?;
+            let output = match output_json {
+                true => result.serialize_json()?,
+                false => result.display(),
+            };
+            if !result.is_success() {
+                println!("{}", output);
+                anyhow::bail!(
+                    "{} out of {} composable components failed to build",
+                    result.failed(),
+                    result.components.len()
+                );
+            }
+            Ok(Some(output))
         }
         Command::GenerateMetadata {} => Err(anyhow::anyhow!(
             "Command deprecated, use `cargo contract build` instead"
         )),
         #[cfg(feature = "extrinsics")]
         Command::Deploy {
-            extrinsic_opts,
+            network_opts,
             wasm_path,
         } => {
+            let network = network_opts.network()?;
+            let extrinsic_opts = &network_opts.extrinsic_opts(&network)?;
             let code_hash = cmd::execute_deploy(extrinsic_opts, wasm_path.as_ref())?;
             Ok(Some(format!("Code hash: {:?}", code_hash)))
         }
         #[cfg(feature = "extrinsics")]
-        Command::ComposableDeploy { suri } => {
-            let manifest_path = Default::default();
-            let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+        Command::ComposableDeploy {
+            manifest_path,
+            compose,
+            network,
+            suri,
+            password,
+            dry_run,
+            output_json,
+        } => {
+            let manifest_path = ManifestPath::try_from(manifest_path.as_ref())?;
+            if *dry_run {
+                let plan = cmd::composable_deploy::plan(&manifest_path, compose)?;
+                return match output_json {
+                    true => Ok(Some(plan.serialize_json()?)),
+                    false => Ok(Some(plan.display())),
+                };
+            }
+            let network = network::select(&manifest_path, network.as_deref())?;
+            let (suri, password) = composable_signer(&network, suri, password)?;
             println!(
                 "{}",
                 "Deploy composable components to appointed urls"
                     .bright_blue()
                     .bold(),
             );
-            let composable_schedule = crate_metadata.clone().t3rn_composable_schedule
-                .expect("Failed to read composable metadata from JSON using serde. Make sure your Cargo.toml follows the composable metadata format");
-            match composable_schedule.deploy {
-                Some(deploy_schedule) => {
-                    for deploy in deploy_schedule {
-                        println!("Deploying: {:?}", deploy);
-                        let component_extrinsic_opts = ExtrinsicOpts {
-                            url: url::Url::parse(&deploy.url)?,
-                            suri: suri.to_string(),
-                            password: None,
-                        };
-                        let dest_wasm_path = cmd::composable_build::get_dest_wasm_path(
-                            deploy.compose.clone(),
-                            &crate_metadata.clone(),
-                        );
-                        let code_hash =
-                            cmd::execute_deploy(&component_extrinsic_opts, Some(&dest_wasm_path))?;
-                        println!(
-                            "{} - {} {:?}",
-                            deploy.compose.bright_blue().bold(),
-                            "successfully deployed byte code with hash: ".bright_blue(),
-                            code_hash
-                        );
-                    }
-                    Ok(Some(format!(
-                        "All components successfully deployed for {:?}",
-                        suri
-                    )))
-                }
-                None => Err(anyhow::anyhow!(
-                    "Nothing to deploy. Empty deploy key of composable metadata."
-                )),
+            let deployed = cmd::composable_deploy::execute(
+                &manifest_path,
+                &suri,
+                password.as_deref(),
+                compose,
+            )?;
+            let summary = deployed
+                .iter()
+                .map(|d| format!("\n\t{} ({}): {:?}", d.compose, d.vm, d.code_hash))
+                .collect::<String>();
+            Ok(Some(format!(
+                "All components successfully deployed for {:?}{}",
+                suri, summary
+            )))
+        }
+        #[cfg(feature = "extrinsics")]
+        Command::ComposableExec {
+            manifest_path,
+            network,
+            suri,
+            password,
+        } => {
+            let manifest_path = ManifestPath::try_from(manifest_path.as_ref())?;
+            let network = network::select(&manifest_path, network.as_deref())?;
+            let (suri, password) = composable_signer(&network, suri, password)?;
+            println!(
+                "{}",
+                "Execute composable components via their gateways"
+                    .bright_blue()
+                    .bold(),
+            );
+            let result = cmd::composable_exec::execute(
+                &manifest_path,
+                &suri,
+                password.as_deref(),
+                &network,
+            )?;
+            if !result.is_success() {
+                println!("{}", result.display());
+                anyhow::bail!("Executing the composable schedule failed");
             }
+            Ok(Some(result.display()))
+        }
+        Command::ComposableValidate { manifest_path } => {
+            let manifest_path = ManifestPath::try_from(manifest_path.as_ref())?;
+            let result = cmd::composable_validate::execute(&manifest_path)?;
+            Ok(Some(result.display()))
         }
         #[cfg(feature = "extrinsics")]
         Command::Instantiate {
-            extrinsic_opts,
+            network_opts,
             endowment,
+            gas,
             code_hash,
-            gas_limit,
-            data,
+            code,
+            salt,
+            call_data,
+            dry_run,
+            output_json,
         } => {
+            let network = network_opts.network()?;
+            let extrinsic_opts = &network_opts.extrinsic_opts(&network)?;
+            let gas = gas.or(&network);
+            let salt = salt.as_ref().map(|salt| salt.0.as_slice());
+            let mut call_data = call_data.clone();
+            let code = match code {
+                Some(path) => {
+                    // The constructors of a bundle are described by the bundle itself.
+                    if call_data.metadata.is_none() && cmd::contract_abi::is_bundle(path) {
+                        call_data.metadata = Some(path.clone());
+                    }
+                    Some(cmd::contract_abi::load_contract_code(Some(path))?)
+                }
+                None => None,
+            };
+            let data = call_data.constructor_data()?;
+            let instantiate_code = match (code_hash, &code) {
+                (Some(code_hash), _) => cmd::dry_run::InstantiateCode::Existing(H256(code_hash.0)),
+                (None, Some(code)) => cmd::dry_run::InstantiateCode::Upload(code),
+                (None, None) => anyhow::bail!("Either --code-hash or --code must be supplied"),
+            };
+            if *dry_run {
+                let result = cmd::dry_run::instantiate(
+                    extrinsic_opts,
+                    *endowment,
+                    gas.dry_run_limit(),
+                    instantiate_code,
+                    data,
+                    salt.unwrap_or_default(),
+                )?;
+                return match output_json {
+                    true => Ok(Some(result.serialize_json()?)),
+                    false => Ok(Some(result.display())),
+                };
+            }
+
+            // The gas is estimated before the code is uploaded, so a failing instantiation does
+            // not leave the code stored on chain.
+            let gas_limit = gas.limit(|gas_limit| {
+                cmd::dry_run::instantiate(
+                    extrinsic_opts,
+                    *endowment,
+                    gas_limit,
+                    instantiate_code,
+                    data.clone(),
+                    salt.unwrap_or_default(),
+                )
+            })?;
+            let code_hash = match (code_hash, &code) {
+                (Some(code_hash), _) => H256(code_hash.0),
+                (None, Some(code)) => {
+                    let deployed = cmd::deploy::deploy_unless_stored(extrinsic_opts, code)?;
+                    let status = match deployed.uploaded {
+                        true => "uploaded",
+                        false => "already stored on chain, upload skipped",
+                    };
+                    println!(
+                        "{} {:?} ({})",
+                        "Code hash:".bright_blue().bold(),
+                        deployed.code_hash,
+                        status
+                    );
+                    deployed.code_hash
+                }
+                (None, None) => unreachable!("either the code hash or the code is supplied; qed"),
+            };
             let contract_account = cmd::execute_instantiate(
                 extrinsic_opts,
                 *endowment,
-                *gas_limit,
-                *code_hash,
-                data.clone(),
+                gas_limit,
+                code_hash,
+                data,
+                salt,
             )?;
-            Ok(Some(format!("Contract account: {:?}", contract_account)))
+            Ok(Some(format!(
+                "Code hash: {:?}\n\tContract account: {:?}",
+                code_hash, contract_account
+            )))
+        }
+        Command::Address(address) => {
+            let result = address.exec()?;
+            match address.output_json {
+                true => Ok(Some(result.serialize_json()?)),
+                false => Ok(Some(result.display())),
+            }
         }
         #[cfg(feature = "extrinsics")]
         Command::CallRuntimeGateway {
-            extrinsic_opts,
+            network_opts,
             target,
             requester,
             wasm_path,
             phase,
             value,
-            gas_limit,
-            data,
+            gas,
+            call_data,
         } => {
-            let code = cmd::deploy::load_contract_code(wasm_path.as_ref())?;
+            let network = network_opts.network()?;
+            let extrinsic_opts = &network_opts.extrinsic_opts(&network)?;
+            let code = cmd::contract_abi::load_contract_code(wasm_path.as_ref())?;
 
             let pair_target = sr25519::Pair::from_string(target, None)
                 .map_err(|_| anyhow::anyhow!("Target account read string error"))?;
@@ -776,31 +1308,48 @@ fn exec(cmd: Command) -> Result<Option<String>> {
             let pair_requester = sr25519::Pair::from_string(requester, None)
                 .map_err(|_| anyhow::anyhow!("Requester account read string error"))?;
 
+            let target = AccountId32::from(pair_target.public());
+            let value = value.or(network.value).unwrap_or_default();
+            let data = call_data.message_data(HexData(vec![0]))?;
+            let fallback = cmd::composable_exec::ExecGateway::Runtime.fallback_gas_limit();
+            let gas_limit = gas.or(&network).gateway_limit(fallback, |gas_limit| {
+                cmd::dry_run::gateway_call(
+                    extrinsic_opts,
+                    target.clone(),
+                    &code,
+                    value,
+                    gas_limit,
+                    data.clone(),
+                )
+            })?;
             let res = cmd::execute_call(
                 extrinsic_opts,
                 AccountId32::from(pair_requester.public()),
-                AccountId32::from(pair_target.public()),
+                target,
                 *phase,
                 &code,
-                *value,
-                *gas_limit,
-                data.clone(),
+                value,
+                gas_limit,
+                data,
             )?;
 
             Ok(Some(format!("CallRuntimeGateway result: {:?}", res)))
         }
         #[cfg(feature = "extrinsics")]
         Command::CallContractsGateway {
-            extrinsic_opts,
+            network_opts,
             target,
             requester,
             wasm_path,
             phase,
             value,
-            gas_limit,
-            data,
+            gas,
+            call_data,
+            output_json,
         } => {
-            let code = match cmd::deploy::load_contract_code(wasm_path.as_ref()) {
+            let network = network_opts.network()?;
+            let extrinsic_opts = &network_opts.extrinsic_opts(&network)?;
+            let code = match cmd::contract_abi::load_contract_code(wasm_path.as_ref()) {
                 Ok(loaded_code) => loaded_code,
                 Err(_) => {
                     println!(
@@ -816,36 +1365,76 @@ fn exec(cmd: Command) -> Result<Option<String>> {
                 target,
                 target.clone().0.as_slice()
             );
-            let res = cmd::execute_contract_call(
+            let target = AccountId32::from(sr25519::Public::from_slice(target.0.as_slice()));
+            let value = value.or(network.value).unwrap_or_default();
+            let data = call_data.message_data(HexData(vec![0]))?;
+            let fallback = cmd::composable_exec::ExecGateway::Contracts.fallback_gas_limit();
+            let gas_limit = gas.or(&network).gateway_limit(fallback, |gas_limit| {
+                cmd::dry_run::gateway_call(
+                    extrinsic_opts,
+                    target.clone(),
+                    &code,
+                    value,
+                    gas_limit,
+                    data.clone(),
+                )
+            })?;
+            let (execution_stamp, events) = cmd::execute_contract_call(
                 extrinsic_opts,
                 AccountId32::from(pair_requester.public()),
-                AccountId32::from(sr25519::Public::from_slice(target.0.as_slice())),
+                target,
                 *phase,
                 &code,
-                *value,
-                *gas_limit,
-                data.clone(),
+                value,
+                gas_limit,
+                data,
             )?;
 
-            Ok(Some(format!("CallRuntimeGateway result: {:?}", res)))
+            let abi = call_data.decoding_abi()?;
+            let result = cmd::CallResult::new(Some(execution_stamp), events, abi.as_ref());
+            match output_json {
+                true => Ok(Some(result.serialize_json()?)),
+                false => Ok(Some(result.display())),
+            }
         }
         #[cfg(feature = "extrinsics")]
         Command::CallContract {
-            extrinsic_opts,
+            network_opts,
             target,
             value,
-            gas_limit,
-            data,
+            gas,
+            call_data,
+            dry_run,
+            output_json,
         } => {
-            let res = cmd::call_regular_contract(
-                extrinsic_opts,
-                AccountId32::from(sr25519::Public::from_slice(target.0.as_slice())),
-                *value,
-                *gas_limit,
-                data.clone(),
-            )?;
-
-            Ok(Some(format!("Call regular contract result: {:?}", res)))
+            let network = network_opts.network()?;
+            let extrinsic_opts = &network_opts.extrinsic_opts(&network)?;
+            let gas = gas.or(&network);
+            let value = value.or(network.value).unwrap_or_default();
+            let dest = AccountId32::from(sr25519::Public::from_slice(target.0.as_slice()));
+            let data = call_data.message_data(HexData(vec![0]))?;
+            let dry_run_call = |gas_limit| {
+                cmd::dry_run::call(extrinsic_opts, dest.clone(), value, gas_limit, data.clone())
+            };
+            if *dry_run {
+                let mut result = dry_run_call(gas.dry_run_limit())?;
+                if result.success {
+                    result.return_value = call_data.return_value(&result.data()?)?;
+                }
+                return match output_json {
+                    true => Ok(Some(result.serialize_json()?)),
+                    false => Ok(Some(result.display())),
+                };
+            }
+            let gas_limit = gas.limit(dry_run_call)?;
+            let events = cmd::call_regular_contract(extrinsic_opts, dest, value, gas_limit, data)?;
+
+            let abi = call_data.decoding_abi()?;
+            let result = cmd::CallResult::new(None, events, abi.as_ref());
+            match output_json {
+                true => Ok(Some(result.serialize_json()?)),
+                false => Ok(Some(result.display())),
+            }
         }
     }
 }
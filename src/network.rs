@@ -0,0 +1,247 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::{crate_metadata::deserialize_from_str, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::PathBuf};
+use url::Url;
+
+/// Name of the user-level config file in the home directory.
+pub const USER_CONFIG_FILE: &str = ".cargo-contract.toml";
+
+/// Runtime the nodes of a network are running.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuntimeType {
+    ContractsTemplate,
+    DefaultNode,
+}
+
+/// Profile of a named network, supplying defaults for the extrinsic commands.
+///
+/// ```toml
+/// [package.metadata.contract.networks.local]
+/// url = "ws://localhost:9944"
+/// suri = "//Alice"
+/// gas = 500000000000
+/// value = "1000000000000000000000"
+/// runtime = "contracts-template"
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct NetworkConfig {
+    /// Websockets url of a node of the network.
+    pub url: Option<Url>,
+    /// Secret key URI of the default signer.
+    pub suri: Option<String>,
+    /// Password for the secret key of the default signer.
+    pub password: Option<String>,
+    /// Gas limit of contract calls and instantiations, instead of estimating it.
+    pub gas: Option<u64>,
+    /// Value transferred by contract calls. Values exceeding the integers of TOML are written as
+    /// strings.
+    #[serde(default, deserialize_with = "deserialize_from_str")]
+    pub value: Option<u128>,
+    /// Runtime of the network, `contracts-template` unless set.
+    pub runtime: Option<RuntimeType>,
+}
+
+impl NetworkConfig {
+    /// Returns the settings of `self`, falling back to those of `defaults` where unset.
+    pub fn or(self, defaults: &NetworkConfig) -> NetworkConfig {
+        NetworkConfig {
+            url: self.url.or_else(|| defaults.url.clone()),
+            suri: self.suri.or_else(|| defaults.suri.clone()),
+            password: self.password.or_else(|| defaults.password.clone()),
+            gas: self.gas.or(defaults.gas),
+            value: self.value.or(defaults.value),
+            runtime: self.runtime.or(defaults.runtime),
+        }
+    }
+}
+
+/// The user-level config file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UserConfig {
+    #[serde(default)]
+    networks: BTreeMap<String, NetworkConfig>,
+}
+
+/// Network profiles of the user-level config file and of the project manifest.
+#[derive(Debug, Default)]
+pub struct Networks {
+    networks: BTreeMap<String, NetworkConfig>,
+}
+
+impl Networks {
+    /// Loads the profiles of `~/.cargo-contract.toml`, overridden per network by those in
+    /// `[package.metadata.contract.networks]` of the manifest, if it exists.
+    pub fn load(manifest_path: &ManifestPath) -> Result<Self> {
+        let user = match user_config_path() {
+            Some(path) if path.exists() => {
+                let toml = fs::read_to_string(&path)?;
+                parse_user_config(&toml).context(format!("Invalid {}", path.display()))?
+            }
+            _ => BTreeMap::new(),
+        };
+        let project = match manifest_path.as_ref().exists() {
+            true => {
+                let toml = fs::read_to_string(manifest_path)?;
+                parse_project_networks(&toml).context(format!(
+                    "Invalid [package.metadata.contract.networks] in {}",
+                    manifest_path.as_ref().display()
+                ))?
+            }
+            false => BTreeMap::new(),
+        };
+        Ok(Self::merge(user, project))
+    }
+
+    fn merge(
+        mut networks: BTreeMap<String, NetworkConfig>,
+        project: BTreeMap<String, NetworkConfig>,
+    ) -> Self {
+        for (name, config) in project {
+            let config = match networks.get(&name) {
+                Some(user) => config.or(user),
+                None => config,
+            };
+            networks.insert(name, config);
+        }
+        Networks { networks }
+    }
+
+    /// Returns the profile of the network `name`.
+    pub fn get(&self, name: &str) -> Result<&NetworkConfig> {
+        self.networks
+            .get(name)
+            .ok_or_else(|| match self.networks.is_empty() {
+                true => anyhow::anyhow!(
+                    "No network '{}', no networks are configured in \
+                     [package.metadata.contract.networks] or ~/{}",
+                    name,
+                    USER_CONFIG_FILE
+                ),
+                false => anyhow::anyhow!(
+                    "No network '{}', available networks are: {}",
+                    name,
+                    self.networks.keys().cloned().collect::<Vec<_>>().join(", ")
+                ),
+            })
+    }
+}
+
+/// Returns the profile of the network `name`, an empty profile if no network is selected.
+pub fn select(manifest_path: &ManifestPath, name: Option<&str>) -> Result<NetworkConfig> {
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(NetworkConfig::default()),
+    };
+    let config = Networks::load(manifest_path)?.get(name)?.clone();
+    if let Some(RuntimeType::DefaultNode) = config.runtime {
+        anyhow::bail!(
+            "Network '{}' runs the default-node runtime, the extrinsic commands only support \
+             contracts-template runtimes",
+            name
+        );
+    }
+    Ok(config)
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(USER_CONFIG_FILE))
+}
+
+fn parse_user_config(toml: &str) -> Result<BTreeMap<String, NetworkConfig>> {
+    let config: UserConfig = toml::from_str(toml)?;
+    Ok(config.networks)
+}
+
+fn parse_project_networks(toml: &str) -> Result<BTreeMap<String, NetworkConfig>> {
+    let manifest: toml::Value = toml::from_str(toml)?;
+    let networks = manifest
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("contract"))
+        .and_then(|contract| contract.get("networks"));
+    match networks {
+        Some(networks) => Ok(networks.clone().try_into()?),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_project_networks, parse_user_config, Networks, RuntimeType};
+
+    #[test]
+    fn project_networks_override_user_networks() {
+        let user = parse_user_config(
+            r#"
+            [networks.local]
+            url = "ws://localhost:9944"
+            suri = "//Alice"
+            gas = 100
+
+            [networks.rococo]
+            url = "wss://rococo.example.com"
+            value = 10
+            "#,
+        )
+        .unwrap();
+        let project = parse_project_networks(
+            r#"
+            [package]
+            name = "flipper"
+
+            [package.metadata.contract.networks.local]
+            suri = "//Bob"
+            value = "1000000000000000000000"
+            runtime = "contracts-template"
+            "#,
+        )
+        .unwrap();
+
+        let networks = Networks::merge(user, project);
+        let local = networks.get("local").unwrap();
+        assert_eq!(local.url.as_ref().unwrap().as_str(), "ws://localhost:9944/");
+        assert_eq!(local.suri.as_deref(), Some("//Bob"));
+        assert_eq!(local.gas, Some(100));
+        assert_eq!(local.value, Some(1_000_000_000_000_000_000_000));
+        assert_eq!(local.runtime, Some(RuntimeType::ContractsTemplate));
+        assert!(networks.get("rococo").unwrap().suri.is_none());
+        assert_eq!(networks.get("rococo").unwrap().value, Some(10));
+
+        let err = networks.get("westend").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No network 'westend', available networks are: local, rococo"
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_reported() {
+        let err = parse_user_config("[networks.local]\nuri = \"ws://localhost:9944\"").unwrap_err();
+        assert!(err.to_string().contains("unknown field `uri`"), "{}", err);
+        assert!(parse_project_networks("[package]\nname = \"flipper\"")
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -16,25 +16,52 @@
 
 use crate::Verbosity;
 use anyhow::{Context, Result};
-use rustc_version::Channel;
-use std::{ffi::OsStr, path::Path, process::Command};
+use rustc_version::{Channel, VersionMeta};
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use walkdir::WalkDir;
 
 /// Check whether the current rust channel is valid: `nightly` is recommended.
-pub fn assert_channel() -> Result<()> {
-    let meta = rustc_version::version_meta()?;
-    match meta.channel {
+pub fn assert_channel(toolchain: Option<&str>) -> Result<()> {
+    assert_nightly(rustc_version_meta(toolchain)?.channel)
+}
+
+/// Check whether contracts can be built with a compiler of the `channel`.
+pub(crate) fn assert_nightly(channel: Channel) -> Result<()> {
+    match channel {
         Channel::Dev | Channel::Nightly => Ok(()),
         Channel::Stable | Channel::Beta => {
             anyhow::bail!(
                 "cargo-contract cannot build using the {:?} channel. \
                 Switch to nightly. \
                 See https://github.com/paritytech/cargo-contract#build-requires-the-nightly-toolchain",
-                format!("{:?}", meta.channel).to_lowercase(),
+                format!("{:?}", channel).to_lowercase(),
             );
         }
     }
 }
 
+/// Returns the version of rustc of the rustup `toolchain`, of the default toolchain if `None`.
+pub(crate) fn rustc_version_meta(toolchain: Option<&str>) -> Result<VersionMeta> {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let mut cmd = Command::new(rustc);
+    cmd.envs(toolchain_env(toolchain));
+    Ok(VersionMeta::for_command(cmd)?)
+}
+
+/// Returns the environment variables selecting the rustup `toolchain`, if any.
+///
+/// Cargo and rustc are invoked via the rustup proxies, which pick up the toolchain.
+pub(crate) fn toolchain_env(toolchain: Option<&str>) -> Vec<(String, String)> {
+    toolchain
+        .map(|toolchain| ("RUSTUP_TOOLCHAIN".to_string(), toolchain.to_string()))
+        .into_iter()
+        .collect()
+}
+
 /// Run cargo with the supplied args
 ///
 /// If successful, returns the stdout bytes
@@ -49,22 +76,27 @@ where
     S: AsRef<OsStr>,
     P: AsRef<Path>,
 {
-    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
-    let mut cmd = Command::new(cargo);
-    if let Some(path) = working_dir {
-        log::debug!("Setting cargo working dir to '{}'", path.as_ref().display());
-        cmd.current_dir(path);
-    }
-
-    cmd.arg(command);
-    cmd.args(args);
-    match verbosity {
-        Verbosity::Quiet => cmd.arg("--quiet"),
-        Verbosity::Verbose => cmd.arg("--verbose"),
-        Verbosity::Default => &mut cmd,
-    };
+    invoke_cargo_with_env(command, args, working_dir, verbosity, &[])
+}
 
-    log::info!("Invoking cargo: {:?}", cmd);
+/// Run cargo with the supplied args and environment variables
+///
+/// The environment variables are only set for this invocation, not for the whole process.
+/// If successful, returns the stdout bytes
+pub(crate) fn invoke_cargo_with_env<I, S, P>(
+    command: &str,
+    args: I,
+    working_dir: Option<P>,
+    verbosity: Verbosity,
+    env: &[(String, String)],
+) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = S> + std::fmt::Debug,
+    S: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    let mut cmd = cargo_command(command, args, working_dir, verbosity);
+    cmd.envs(env.iter().map(|(key, value)| (key, value)));
 
     let child = cmd
         // capture the stdout to return from this function as bytes
@@ -84,6 +116,87 @@ where
     }
 }
 
+/// Output of a cargo invocation, see [`invoke_cargo_captured`].
+pub(crate) struct CargoOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: String,
+}
+
+/// Run cargo with the supplied args and environment variables, capturing its stderr instead of
+/// passing it through
+///
+/// Used for cargo invocations running concurrently, whose output would interleave otherwise. The
+/// environment variables are only set for this invocation, not for the whole process.
+/// If successful, returns the stdout bytes and the captured stderr. If not, the error contains
+/// the captured stderr.
+pub(crate) fn invoke_cargo_captured<I, S, P>(
+    command: &str,
+    args: I,
+    working_dir: Option<P>,
+    verbosity: Verbosity,
+    env: &[(String, String)],
+) -> Result<CargoOutput>
+where
+    I: IntoIterator<Item = S> + std::fmt::Debug,
+    S: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    let mut cmd = cargo_command(command, args, working_dir, verbosity);
+    cmd.envs(env.iter().map(|(key, value)| (key, value)));
+
+    let output = cmd
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .output()
+        .context(format!("Error executing `{:?}`", cmd))?;
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+
+    if output.status.success() {
+        Ok(CargoOutput {
+            stdout: output.stdout,
+            stderr,
+        })
+    } else {
+        anyhow::bail!(
+            "`{:?}` failed with exit code: {:?}\n{}",
+            cmd,
+            output.status.code(),
+            stderr
+        );
+    }
+}
+
+/// Returns the cargo command with the supplied args, to be run in `working_dir`.
+fn cargo_command<I, S, P>(
+    command: &str,
+    args: I,
+    working_dir: Option<P>,
+    verbosity: Verbosity,
+) -> Command
+where
+    I: IntoIterator<Item = S> + std::fmt::Debug,
+    S: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut cmd = Command::new(cargo);
+    if let Some(path) = working_dir {
+        log::debug!("Setting cargo working dir to '{}'", path.as_ref().display());
+        cmd.current_dir(path);
+    }
+
+    cmd.arg(command);
+    cmd.args(args);
+    match verbosity {
+        Verbosity::Quiet => cmd.arg("--quiet"),
+        Verbosity::Verbose => cmd.arg("--verbose"),
+        Verbosity::Default => &mut cmd,
+    };
+
+    log::info!("Invoking cargo: {:?}", cmd);
+    cmd
+}
+
 /// Returns the base name of the path.
 pub(crate) fn base_name(path: &Path) -> &str {
     path.file_name()
@@ -92,6 +205,52 @@ pub(crate) fn base_name(path: &Path) -> &str {
         .expect("must be valid utf-8")
 }
 
+/// Returns all files below `root` with one of the supplied extensions, sorted by path.
+///
+/// Hidden directories and everything below `exclude` (usually the target directory) are skipped.
+pub(crate) fn collect_source_files(
+    root: &Path,
+    exclude: &Path,
+    extensions: &[&str],
+) -> Vec<PathBuf> {
+    let mut files = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let hidden = entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.');
+            !hidden && !entry.path().starts_with(exclude)
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(
+            |path| matches!(path.extension(), Some(ext) if extensions.iter().any(|e| ext == *e)),
+        )
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Returns the output of `wasm-opt --version`, if `wasm-opt` is installed.
+pub(crate) fn wasm_opt_version() -> Option<String> {
+    Command::new("wasm-opt")
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Returns the commit checked out in the git repository containing `dir`, if any.
+pub(crate) fn git_commit(dir: &Path) -> Option<String> {
+    Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 /// Prints to stdout if `verbosity.is_verbose()` is `true`.
 #[macro_export]
 macro_rules! maybe_println {